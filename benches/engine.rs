@@ -0,0 +1,119 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use wasm_board_games::{
+    perft, Board, Cell, Coords, FiarBitboardState, FiarGameState, GameOutcome, GameState,
+    Heuristic, T3GameState, TreeEvaluator,
+};
+
+/// Four-in-a-row position after a few moves in the middle of the board.
+fn fiar_midgame() -> FiarGameState {
+    FiarGameState::from_notation("7/7/7/3O3/2XXO2/1OXXO2 X").expect("Valid notation")
+}
+
+fn bench_expand(c: &mut Criterion) {
+    let t3_state = T3GameState::default();
+    c.bench_function("expand t3 empty", |b| {
+        b.iter(|| black_box(&t3_state).expand())
+    });
+
+    let fiar_state = fiar_midgame();
+    c.bench_function("expand fiar midgame", |b| {
+        b.iter(|| black_box(&fiar_state).expand())
+    });
+
+    let bitboard_state = FiarBitboardState::from_notation("7/7/7/3O3/2XXO2/1OXXO2 X").unwrap();
+    c.bench_function("expand fiar bitboard midgame", |b| {
+        b.iter(|| black_box(&bitboard_state).expand())
+    });
+
+    // The win check alone, which scans lines on the board but only shifts
+    // masks on the bitboard
+    c.bench_function("outcome fiar midgame", |b| {
+        b.iter(|| black_box(&fiar_state).outcome())
+    });
+    c.bench_function("outcome fiar bitboard midgame", |b| {
+        b.iter(|| black_box(&bitboard_state).outcome())
+    });
+
+    // Generating children and checking them for a winner is the work per node
+    // in every search
+    c.bench_function("expand and outcome fiar midgame", |b| {
+        b.iter(|| {
+            black_box(&fiar_state)
+                .expand()
+                .iter()
+                .map(|child| child.outcome())
+                .collect::<Vec<GameOutcome>>()
+        })
+    });
+    c.bench_function("expand and outcome fiar bitboard midgame", |b| {
+        b.iter(|| {
+            black_box(&bitboard_state)
+                .expand()
+                .iter()
+                .map(|child| child.outcome())
+                .collect::<Vec<GameOutcome>>()
+        })
+    });
+    // Without collecting the children the bitboard does not allocate at all
+    c.bench_function("children and outcome fiar bitboard midgame", |b| {
+        b.iter(|| {
+            black_box(&bitboard_state)
+                .children()
+                .filter(|child| child.outcome().is_over())
+                .count()
+        })
+    });
+
+    c.bench_function("lines heuristic fiar midgame", |b| {
+        b.iter(|| black_box(&fiar_state).heuristic_value(Heuristic::Lines))
+    });
+    c.bench_function("lines heuristic fiar bitboard midgame", |b| {
+        b.iter(|| black_box(&bitboard_state).heuristic_value(Heuristic::Lines))
+    });
+
+    c.bench_function("perft fiar depth 4", |b| {
+        b.iter(|| perft(black_box(&FiarGameState::default()), 4))
+    });
+    c.bench_function("perft fiar bitboard depth 4", |b| {
+        b.iter(|| perft(black_box(&FiarBitboardState::default()), 4))
+    });
+}
+
+fn bench_line_winner(c: &mut Criterion) {
+    let (board, _) = Board::from_notation("7/7/7/3O3/2XXO2/1OXXO2 X").expect("Valid notation");
+    let last_move = Coords { row: 4, col: 3 };
+    c.bench_function("line_winner fiar midgame", |b| {
+        b.iter(|| black_box(&board).line_winner(black_box(&last_move), 4))
+    });
+
+    let mut board = Board::new(15, 15);
+    for col in 3..7 {
+        board.set_cell(7, col, Cell::X);
+    }
+    let last_move = Coords { row: 7, col: 6 };
+    c.bench_function("line_winner gomoku", |b| {
+        b.iter(|| black_box(&board).line_winner(black_box(&last_move), 5))
+    });
+}
+
+fn bench_evaluate_states(c: &mut Criterion) {
+    let mut tree_eval = TreeEvaluator::new(T3GameState::default());
+    tree_eval.expand_states_by(0, 9);
+    c.bench_function("evaluate_states t3 full tree", |b| {
+        b.iter(|| tree_eval.evaluate_states(black_box(0)))
+    });
+
+    let mut tree_eval = TreeEvaluator::new(FiarGameState::default());
+    tree_eval.expand_states_by(0, 5);
+    c.bench_function("evaluate_states fiar depth 5", |b| {
+        b.iter(|| tree_eval.evaluate_states(black_box(0)))
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_expand,
+    bench_line_winner,
+    bench_evaluate_states
+);
+criterion_main!(benches);
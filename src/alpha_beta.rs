@@ -0,0 +1,370 @@
+use crate::{now_ms, Cell, GameState, Heuristic, TranspositionTable, X_WIN_VALUE};
+use std::cmp::Reverse;
+
+/// Kind of value stored for a searched position.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Bound {
+    Exact,
+    /// The search failed high, the exact value is at least the stored one.
+    Lower,
+    /// The search failed low, the exact value is at most the stored one.
+    Upper,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct SearchEntry {
+    depth: usize,
+    value: i32,
+    bound: Bound,
+}
+
+/// Limit for an iterative deepening search.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SearchBudget {
+    /// Stop after the given number of milliseconds.
+    Millis(f64),
+    /// Stop after visiting the given number of nodes, which is deterministic.
+    Nodes(usize),
+}
+
+/// Best child found by the deepest finished iteration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeepeningResult {
+    /// Position of the best child in the searched children
+    pub best_child: usize,
+    pub value: i32,
+    pub depth: usize,
+}
+
+/// Depth-first minimax search with alpha-beta pruning.
+///
+/// In contrast to the `TreeEvaluator`, the search does not materialize the
+/// game tree. Instead, it walks the tree depth-first and only keeps the
+/// states on the current path in memory. Children are ordered by their
+/// heuristic value before they are searched so that promising moves come
+/// first and cut-offs happen early. Values of searched positions are cached
+/// in a transposition table so that positions reached by different move
+/// orders are only searched once.
+#[derive(Debug, Default)]
+pub struct AlphaBetaSearch {
+    nodes: usize,
+    table: TranspositionTable<SearchEntry>,
+    heuristic: Heuristic,
+    deadline_ms: Option<f64>,
+    node_limit: Option<usize>,
+    /// Set when a limit was hit, the current iteration is incomplete then
+    aborted: bool,
+    /// Set when a search stopped at the depth limit in a non-final state
+    horizon_reached: bool,
+}
+
+impl AlphaBetaSearch {
+    /// Create a search which estimates positions at the depth limit with
+    /// `heuristic`.
+    pub fn new(heuristic: Heuristic) -> Self {
+        Self {
+            nodes: 0,
+            table: TranspositionTable::new(),
+            heuristic,
+            deadline_ms: None,
+            node_limit: None,
+            aborted: false,
+            horizon_reached: false,
+        }
+    }
+
+    /// Number of nodes visited since the search was created.
+    pub fn nodes(&self) -> usize {
+        self.nodes
+    }
+
+    /// Number of positions whose value was found in the transposition table.
+    pub fn transposition_hits(&self) -> usize {
+        self.table.hits()
+    }
+
+    /// Number of positions which were not found in the transposition table.
+    pub fn transposition_misses(&self) -> usize {
+        self.table.misses()
+    }
+
+    /// Identify the best of `children` for the side to move after `state`.
+    ///
+    /// `depth` counts the plies including the move to the children, so
+    /// searching with the same depth as the tree was expanded to in the
+    /// `TreeEvaluator` yields the same values. The children are searched in
+    /// the given order and only a strictly better value replaces the current
+    /// best child. Thus among equally valued children, the first one is
+    /// returned - just like with the full minimax evaluation.
+    pub fn best_child<T: GameState>(
+        &mut self,
+        state: &T,
+        children: &[&T],
+        depth: usize,
+    ) -> Option<(usize, i32)> {
+        let maximizing = is_maximizing(state.side());
+        let child_depth = depth.saturating_sub(1);
+
+        let mut best: Option<(usize, i32)> = None;
+        for (pos, &child) in children.iter().enumerate() {
+            let value = match (best, maximizing) {
+                (None, _) => self.evaluate(child, child_depth, i32::MIN, i32::MAX),
+                (Some((_, best_value)), true) => {
+                    self.evaluate(child, child_depth, best_value, i32::MAX)
+                }
+                (Some((_, best_value)), false) => {
+                    self.evaluate(child, child_depth, i32::MIN, best_value)
+                }
+            };
+
+            if self.aborted {
+                break;
+            }
+
+            best = match best {
+                Some((_, best_value)) if maximizing && value <= best_value => best,
+                Some((_, best_value)) if !maximizing && value >= best_value => best,
+                _ => Some((pos, value)),
+            };
+        }
+
+        best
+    }
+
+    /// Exact value of each of `children` with a search of `depth` plies.
+    ///
+    /// In contrast to `best_child`, every child is searched with the full
+    /// window so that the values of inferior children are exact as well.
+    pub fn child_values<T: GameState>(&mut self, children: &[&T], depth: usize) -> Vec<i32> {
+        let child_depth = depth.saturating_sub(1);
+        children
+            .iter()
+            .map(|&child| self.evaluate(child, child_depth, i32::MIN, i32::MAX))
+            .collect()
+    }
+
+    /// Expected continuation after `state` when both sides play the best
+    /// moves found with a search of `depth` plies.
+    ///
+    /// The line is built by searching the best child on every level again.
+    /// Values found on the first level are cached in the transposition table,
+    /// so this is much cheaper than the initial search.
+    pub fn principal_variation<T: GameState>(&mut self, state: &T, depth: usize) -> Vec<T> {
+        let mut line: Vec<T> = Vec::new();
+        let mut children = state.expand();
+
+        for remaining_depth in (1..=depth).rev() {
+            let parent = line.last().unwrap_or(state);
+            let child_refs: Vec<&T> = children.iter().collect();
+            let best_pos = match self.best_child(parent, &child_refs, remaining_depth) {
+                Some((best_pos, _)) => best_pos,
+                None => break,
+            };
+
+            let best_child = children.swap_remove(best_pos);
+            children = match best_child.outcome().is_over() {
+                true => vec![],
+                false => best_child.expand(),
+            };
+            line.push(best_child);
+        }
+
+        line
+    }
+
+    /// Search `children` of `state` with increasing depth until `budget` is
+    /// used up or `max_depth` is reached.
+    ///
+    /// Returns the best child of the deepest iteration which finished. The
+    /// first iteration always finishes, so there is a result as long as there
+    /// are children. The deepening stops early if the game is solved or if
+    /// an iteration searched every line to its end.
+    pub fn iterative_deepening<T: GameState>(
+        &mut self,
+        state: &T,
+        children: &[&T],
+        max_depth: usize,
+        budget: SearchBudget,
+    ) -> Option<DeepeningResult> {
+        let (deadline_ms, node_limit) = match budget {
+            SearchBudget::Millis(millis) => (Some(now_ms() + millis), None),
+            SearchBudget::Nodes(nodes) => (None, Some(self.nodes + nodes)),
+        };
+
+        let mut result = None;
+        for depth in 1..=max_depth {
+            self.horizon_reached = false;
+            let (best_child, value) = match self.best_child(state, children, depth) {
+                Some(best) if !self.aborted => best,
+                _ => break,
+            };
+            result = Some(DeepeningResult {
+                best_child,
+                value,
+                depth,
+            });
+
+            if value == X_WIN_VALUE || value == -X_WIN_VALUE || !self.horizon_reached {
+                break;
+            }
+
+            // Apply the limits from the second iteration on
+            self.deadline_ms = deadline_ms;
+            self.node_limit = node_limit;
+        }
+
+        self.deadline_ms = None;
+        self.node_limit = None;
+        self.aborted = false;
+
+        result
+    }
+
+    /// Check if the node or time limit is used up.
+    ///
+    /// The time is only checked every few nodes since it is comparatively
+    /// expensive to get in Wasm.
+    fn limit_reached(&self) -> bool {
+        if let Some(node_limit) = self.node_limit {
+            if self.nodes > node_limit {
+                return true;
+            }
+        }
+
+        match self.deadline_ms {
+            Some(deadline_ms) => self.nodes.is_multiple_of(1024) && now_ms() >= deadline_ms,
+            None => false,
+        }
+    }
+
+    /// Evaluate `state` by searching `depth` plies below it.
+    ///
+    /// The returned value is exact if it lies within (`alpha`, `beta`).
+    /// Otherwise, it is a bound on the exact value (fail-soft).
+    pub fn evaluate<T: GameState>(
+        &mut self,
+        state: &T,
+        depth: usize,
+        alpha: i32,
+        beta: i32,
+    ) -> i32 {
+        let value = state.heuristic_value(self.heuristic);
+        self.search(state, value, depth, alpha, beta)
+    }
+
+    /// Search below `state` whose heuristic value is already known from
+    /// ordering the children of its parent.
+    fn search<T: GameState>(
+        &mut self,
+        state: &T,
+        value: i32,
+        depth: usize,
+        mut alpha: i32,
+        mut beta: i32,
+    ) -> i32 {
+        self.nodes += 1;
+
+        if self.aborted || self.limit_reached() {
+            // The value is discarded with the whole iteration
+            self.aborted = true;
+            return value;
+        }
+
+        if value == X_WIN_VALUE || value == -X_WIN_VALUE {
+            return value;
+        }
+
+        if depth == 0 {
+            // Drawn states at the depth limit do not need a deeper search
+            self.horizon_reached |= !state.outcome().is_over();
+            return value;
+        }
+
+        let hash = state.zobrist_hash();
+        if let Some(entry) = self.table.get(hash) {
+            if entry.depth >= depth {
+                match entry.bound {
+                    Bound::Exact => return entry.value,
+                    Bound::Lower if entry.value >= beta => return entry.value,
+                    Bound::Upper if entry.value <= alpha => return entry.value,
+                    _ => (),
+                }
+            }
+        }
+
+        let maximizing = is_maximizing(state.side());
+        let children = self.ordered_children(state, maximizing);
+        if children.is_empty() {
+            return value;
+        }
+
+        let (init_alpha, init_beta) = (alpha, beta);
+        let best_value = if maximizing {
+            let mut best_value = i32::MIN;
+            for (child_value, child) in children.iter() {
+                best_value =
+                    best_value.max(self.search(child, *child_value, depth - 1, alpha, beta));
+                alpha = alpha.max(best_value);
+                if alpha >= beta || self.aborted {
+                    break;
+                }
+            }
+            best_value
+        } else {
+            let mut best_value = i32::MAX;
+            for (child_value, child) in children.iter() {
+                best_value =
+                    best_value.min(self.search(child, *child_value, depth - 1, alpha, beta));
+                beta = beta.min(best_value);
+                if alpha >= beta || self.aborted {
+                    break;
+                }
+            }
+            best_value
+        };
+
+        if self.aborted {
+            // Do not store values of an incomplete search
+            return best_value;
+        }
+
+        let bound = match best_value {
+            v if v <= init_alpha => Bound::Upper,
+            v if v >= init_beta => Bound::Lower,
+            _ => Bound::Exact,
+        };
+        self.table.insert(
+            hash,
+            SearchEntry {
+                depth,
+                value: best_value,
+                bound,
+            },
+        );
+
+        best_value
+    }
+
+    /// Expand `state` and sort the children with their heuristic values so
+    /// that the most promising ones for the side to move come first.
+    fn ordered_children<T: GameState>(&self, state: &T, maximizing: bool) -> Vec<(i32, T)> {
+        let mut children: Vec<(i32, T)> = state
+            .expand()
+            .into_iter()
+            .map(|child| (child.heuristic_value(self.heuristic), child))
+            .collect();
+
+        match maximizing {
+            true => children.sort_by_key(|(value, _)| Reverse(*value)),
+            false => children.sort_by_key(|(value, _)| *value),
+        }
+
+        children
+    }
+}
+
+/// The side of the last move is passed in. If O did the last move, X moves
+/// next and wants to maximize the value.
+fn is_maximizing(last_side: Cell) -> bool {
+    last_side == Cell::O
+}
@@ -0,0 +1,35 @@
+use crate::{BoardMove, Error};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+/// Score of a single legal move.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveScore {
+    pub board_move: BoardMove,
+    /// Minimax value after the move, positive values favor X
+    pub score: i32,
+}
+
+/// Evaluation of a position for hints and an evaluation bar in the UI.
+///
+/// Serialized for JS as
+/// `{ moves: [{ boardMove, score }], principalVariation: [...], value, depth }`.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Analysis {
+    /// All legal moves in the order of the board
+    pub moves: Vec<MoveScore>,
+    /// Expected continuation starting with the best move
+    pub principal_variation: Vec<BoardMove>,
+    /// Value of the position, i.e. the score of the best move
+    pub value: i32,
+    /// Number of plies the analysis looked ahead
+    pub depth: usize,
+}
+
+impl Analysis {
+    pub fn to_js_value(&self) -> Result<JsValue, Error> {
+        Ok(serde_wasm_bindgen::to_value(&self)?)
+    }
+}
@@ -0,0 +1,449 @@
+//! Play tic-tac-toe and Four-in-a-row in the terminal.
+//!
+//! The binary uses the same game interfaces as the web worker, so the engine
+//! behaves exactly like in the browser.
+
+use std::env;
+use std::io::{self, BufRead, Write};
+use std::process;
+
+use wasm_board_games::{
+    set_log_level, Analysis, Board, BoardMove, Cell, Coords, Error, ExpandResult,
+    FiarGameInterface, GameOutcome, LogLevel, SearchResult, T3GameInterface, X_WIN_VALUE,
+};
+
+const USAGE: &str = "\
+Usage: board_games <t3|fiar> [play|selfplay|analyze] [options] [moves...]
+
+Modes:
+  play        Play against the engine (default)
+  selfplay    Let the engine play against itself
+  analyze     Score all moves after the given moves
+
+Options:
+  --engine <x|o>   Side of the engine when playing (default: o)
+  --time-ms <ms>   Thinking time of the engine per move (default: 1000)
+  --log-level <l>  Log engine events up to off, error, warn, info or debug
+                   to stderr (default: warn)
+  --verbose        Same as --log-level debug
+
+Moves are given as `row,col` for tic-tac-toe and as column for Four-in-a-row,
+e.g. `board_games fiar analyze 3 3 2`.";
+
+/// Engine calls shared by the game interfaces.
+trait Engine {
+    fn expand_one_level(&mut self) -> ExpandResult;
+    fn track_move(&mut self, game_move: BoardMove) -> Result<(), Error>;
+    fn get_best_move_within(&mut self, budget_ms: f64) -> Result<SearchResult, Error>;
+    fn analyze(&mut self) -> Analysis;
+
+    /// Expand the tree like the worker does before a search.
+    fn expand_fully(&mut self) {
+        while self.expand_one_level() != ExpandResult::Done {}
+    }
+}
+
+macro_rules! impl_engine {
+    ( $game_if:ty ) => {
+        impl Engine for $game_if {
+            fn expand_one_level(&mut self) -> ExpandResult {
+                <$game_if>::expand_one_level(self)
+            }
+
+            fn track_move(&mut self, game_move: BoardMove) -> Result<(), Error> {
+                <$game_if>::track_move(self, game_move)
+            }
+
+            fn get_best_move_within(&mut self, budget_ms: f64) -> Result<SearchResult, Error> {
+                <$game_if>::get_best_move_within(self, budget_ms)
+            }
+
+            fn analyze(&mut self) -> Analysis {
+                <$game_if>::analyze(self)
+            }
+        }
+    };
+}
+
+impl_engine!(T3GameInterface);
+impl_engine!(FiarGameInterface);
+
+/// Rules of a game as far as the terminal frontend needs them.
+struct Rules {
+    height: u32,
+    width: u32,
+    num_winner: i32,
+    /// Pieces drop to the bottom of the columns
+    gravity: bool,
+}
+
+const T3_RULES: Rules = Rules {
+    height: 3,
+    width: 3,
+    num_winner: 3,
+    gravity: false,
+};
+
+const FIAR_RULES: Rules = Rules {
+    height: 6,
+    width: 7,
+    num_winner: 4,
+    gravity: true,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+    Play,
+    SelfPlay,
+    Analyze,
+}
+
+struct Options {
+    mode: Mode,
+    engine_side: Cell,
+    time_ms: f64,
+    log_level: LogLevel,
+    moves: Vec<String>,
+}
+
+/// Parse the arguments after the game name.
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        mode: Mode::Play,
+        engine_side: Cell::O,
+        time_ms: 1000.0,
+        log_level: LogLevel::Warn,
+        moves: vec![],
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "play" => options.mode = Mode::Play,
+            "selfplay" => options.mode = Mode::SelfPlay,
+            "analyze" => options.mode = Mode::Analyze,
+            "--engine" => {
+                options.engine_side = match args.next().map(|side| side.to_lowercase()) {
+                    Some(side) if side == "x" => Cell::X,
+                    Some(side) if side == "o" => Cell::O,
+                    _ => return Err("--engine expects x or o".to_owned()),
+                }
+            }
+            "--time-ms" => {
+                options.time_ms = args
+                    .next()
+                    .and_then(|time_ms| time_ms.parse().ok())
+                    .ok_or("--time-ms expects a number of milliseconds")?
+            }
+            "--log-level" => {
+                options.log_level = match args.next().map(|level| level.to_lowercase()) {
+                    Some(level) if level == "off" => LogLevel::Off,
+                    Some(level) if level == "error" => LogLevel::Error,
+                    Some(level) if level == "warn" => LogLevel::Warn,
+                    Some(level) if level == "info" => LogLevel::Info,
+                    Some(level) if level == "debug" => LogLevel::Debug,
+                    _ => {
+                        return Err("--log-level expects off, error, warn, info or debug".to_owned())
+                    }
+                }
+            }
+            "--verbose" => options.log_level = LogLevel::Debug,
+            game_move => options.moves.push(game_move.to_owned()),
+        }
+    }
+
+    Ok(options)
+}
+
+/// Parse `input` to the coordinates of a move on `board`.
+///
+/// Returns `None` if the input is malformed or the target cell is taken.
+fn parse_move(rules: &Rules, board: &Board, input: &str) -> Option<Coords> {
+    let coords = if rules.gravity {
+        let col: u32 = input.trim().parse().ok()?;
+        board.first_empty_in_column(col)?
+    } else {
+        let (row, col) = input.trim().split_once(',')?;
+        Coords {
+            row: row.trim().parse().ok()?,
+            col: col.trim().parse().ok()?,
+        }
+    };
+
+    match board.get_cell(coords.row, coords.col) {
+        Ok(Cell::Empty) => Some(coords),
+        _ => None,
+    }
+}
+
+fn format_move(rules: &Rules, board_move: &BoardMove) -> String {
+    match rules.gravity {
+        true => format!("{:?} {}", board_move.side, board_move.coords.col),
+        false => format!(
+            "{:?} {},{}",
+            board_move.side, board_move.coords.row, board_move.coords.col
+        ),
+    }
+}
+
+fn format_value(value: i32) -> String {
+    match value {
+        X_WIN_VALUE => "X wins".to_owned(),
+        v if v == -X_WIN_VALUE => "O wins".to_owned(),
+        v => v.to_string(),
+    }
+}
+
+fn next_side(last_move: &BoardMove) -> Cell {
+    match last_move.side {
+        Cell::X => Cell::O,
+        _ => Cell::X,
+    }
+}
+
+/// Game in the terminal which keeps the board in sync with the engine.
+struct TerminalGame {
+    rules: Rules,
+    engine: Box<dyn Engine>,
+    board: Board,
+    last_move: BoardMove,
+}
+
+impl TerminalGame {
+    fn new(rules: Rules, engine: Box<dyn Engine>) -> Self {
+        let board = Board::new(rules.height, rules.width);
+        Self {
+            rules,
+            engine,
+            board,
+            // We usually start with X, so the "last" was O
+            last_move: BoardMove::new(0, 0, Cell::O),
+        }
+    }
+
+    /// Play the move on `coords` for the side to move.
+    fn play(&mut self, coords: Coords) -> Result<GameOutcome, String> {
+        let game_move = BoardMove {
+            coords,
+            side: next_side(&self.last_move),
+        };
+
+        if let Err(err) = self.engine.track_move(game_move) {
+            let reason = match err {
+                Error::IllegalMove(_, reason) => reason.to_string(),
+                err => err.to_string(),
+            };
+            return Err(format!(
+                "Illegal move {}: {}",
+                format_move(&self.rules, &game_move),
+                reason
+            ));
+        }
+        self.apply(game_move);
+
+        Ok(self.board.outcome(&coords, self.rules.num_winner))
+    }
+
+    /// Let the engine choose and play a move.
+    fn play_engine(&mut self, time_ms: f64) -> Result<GameOutcome, String> {
+        self.engine.expand_fully();
+        let result = self
+            .engine
+            .get_best_move_within(time_ms)
+            .map_err(|err| err.to_string())?;
+        println!(
+            "Engine plays {} (value {}, depth {})",
+            format_move(&self.rules, &result.best_move),
+            format_value(result.value),
+            result.depth
+        );
+        self.apply(result.best_move);
+
+        Ok(self
+            .board
+            .outcome(&result.best_move.coords, self.rules.num_winner))
+    }
+
+    fn apply(&mut self, game_move: BoardMove) {
+        self.board
+            .set_cell(game_move.coords.row, game_move.coords.col, game_move.side);
+        self.last_move = game_move;
+    }
+
+    fn print_analysis(&mut self) {
+        self.engine.expand_fully();
+        let analysis = self.engine.analyze();
+
+        println!(
+            "Value {} at depth {}",
+            format_value(analysis.value),
+            analysis.depth
+        );
+        for move_score in analysis.moves.iter() {
+            println!(
+                "  {:<10} {}",
+                format_move(&self.rules, &move_score.board_move),
+                format_value(move_score.score)
+            );
+        }
+
+        let principal_variation: Vec<String> = analysis
+            .principal_variation
+            .iter()
+            .map(|board_move| format_move(&self.rules, board_move))
+            .collect();
+        println!("Principal variation: {}", principal_variation.join(", "));
+    }
+}
+
+fn print_outcome(outcome: GameOutcome) {
+    match outcome {
+        GameOutcome::Win(side) => println!("{:?} wins!", side),
+        GameOutcome::Draw => println!("Draw!"),
+        GameOutcome::Ongoing => (),
+    }
+}
+
+fn run(game: &mut TerminalGame, options: &Options) -> Result<(), String> {
+    // Moves given on the command line are played before anything else
+    for input in options.moves.iter() {
+        let coords =
+            parse_move(&game.rules, &game.board, input).ok_or(format!("Invalid move {}", input))?;
+        if game.play(coords)?.is_over() {
+            return Err(format!("The game is over after move {}", input));
+        }
+    }
+
+    match options.mode {
+        Mode::Analyze => {
+            print!("{}", game.board);
+            game.print_analysis();
+        }
+        Mode::SelfPlay => loop {
+            print!("{}", game.board);
+            let outcome = game.play_engine(options.time_ms)?;
+            if outcome.is_over() {
+                print!("{}", game.board);
+                print_outcome(outcome);
+                break;
+            }
+        },
+        Mode::Play => {
+            let stdin = io::stdin();
+            let mut lines = stdin.lock().lines();
+
+            loop {
+                print!("{}", game.board);
+                let outcome = if next_side(&game.last_move) == options.engine_side {
+                    game.play_engine(options.time_ms)?
+                } else {
+                    print!("{:?} to move: ", next_side(&game.last_move));
+                    io::stdout().flush().map_err(|err| err.to_string())?;
+
+                    let line = match lines.next() {
+                        Some(line) => line.map_err(|err| err.to_string())?,
+                        None => return Ok(()),
+                    };
+                    match parse_move(&game.rules, &game.board, &line) {
+                        Some(coords) => game.play(coords)?,
+                        None => {
+                            println!("Invalid move {}", line.trim());
+                            continue;
+                        }
+                    }
+                };
+
+                if outcome.is_over() {
+                    print!("{}", game.board);
+                    print_outcome(outcome);
+                    break;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let options = match parse_options(args.get(1..).unwrap_or_default()) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+    set_log_level(options.log_level);
+
+    let (rules, engine): (Rules, Box<dyn Engine>) = match args.first().map(String::as_str) {
+        Some("t3") => (T3_RULES, Box::new(T3GameInterface::new())),
+        Some("fiar") => (FIAR_RULES, Box::new(FiarGameInterface::new())),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    let mut game = TerminalGame::new(rules, engine);
+    if let Err(message) = run(&mut game, &options) {
+        eprintln!("{}", message);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_move, parse_options, Mode, FIAR_RULES, T3_RULES};
+    use wasm_board_games::{Board, Cell, Coords, LogLevel};
+
+    #[test]
+    fn test_parse_move() {
+        let mut board = Board::new(3, 3);
+        board.set_cell(1, 1, Cell::X);
+        assert_eq!(
+            parse_move(&T3_RULES, &board, " 0, 2\n"),
+            Some(Coords { row: 0, col: 2 })
+        );
+        assert_eq!(parse_move(&T3_RULES, &board, "1,1"), None);
+        assert_eq!(parse_move(&T3_RULES, &board, "3,0"), None);
+        assert_eq!(parse_move(&T3_RULES, &board, "2"), None);
+
+        // Pieces drop to the lowest empty cell and full columns are rejected
+        let mut board = Board::new(6, 7);
+        board.set_cell(5, 3, Cell::X);
+        assert_eq!(
+            parse_move(&FIAR_RULES, &board, "3"),
+            Some(Coords { row: 4, col: 3 })
+        );
+        for row in 0..6 {
+            board.set_cell(row, 0, Cell::O);
+        }
+        assert_eq!(parse_move(&FIAR_RULES, &board, "0"), None);
+        assert_eq!(parse_move(&FIAR_RULES, &board, "7"), None);
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let args: Vec<String> = [
+            "analyze",
+            "3",
+            "--time-ms",
+            "200",
+            "--log-level",
+            "info",
+            "4",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+        let options = parse_options(&args).unwrap();
+
+        assert_eq!(options.mode, Mode::Analyze);
+        assert_eq!(options.time_ms, 200.0);
+        assert_eq!(options.log_level, LogLevel::Info);
+        assert_eq!(options.moves, vec!["3", "4"]);
+        assert!(parse_options(&["--engine".to_owned()]).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+//! Generate the opening book of Four-in-a-row with deep offline searches.
+//!
+//! The book is written to stdout in the text format of `OpeningBook`, e.g.
+//! `cargo run --release --bin fiar_book > books/fiar.book`.
+
+use std::env;
+use std::process;
+use std::str::FromStr;
+
+use wasm_board_games::{now_ms, FiarBitboardState, Heuristic, OpeningBook};
+
+const USAGE: &str = "\
+Usage: fiar_book [options]
+
+Options:
+  --plies <n>    Number of moves from the start covered by the book (default: 6)
+  --depth <n>    Plies searched for every position (default: 11)
+  --margin <n>   Recommend moves within this value of the best one (default: 10)";
+
+#[derive(Debug, PartialEq, Eq)]
+struct Options {
+    plies: usize,
+    depth: usize,
+    margin: u32,
+}
+
+/// Parse the value of `option` into its own type, rejecting out-of-range values.
+fn parse_value<T: FromStr>(option: &str, value: Option<&String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("Missing value for {}", option))?;
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value {} for {}", value, option))
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        plies: 6,
+        depth: 11,
+        margin: 10,
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--plies" => options.plies = parse_value(arg, args.next())?,
+            "--depth" => options.depth = parse_value(arg, args.next())?,
+            "--margin" => options.margin = parse_value(arg, args.next())?,
+            _ => return Err(format!("Invalid option {}", arg)),
+        }
+    }
+
+    Ok(options)
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = match parse_options(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+
+    let start_ms = now_ms();
+    let book = OpeningBook::generate(
+        &FiarBitboardState::default(),
+        FiarBitboardState::to_notation,
+        options.plies,
+        options.depth,
+        Heuristic::Lines,
+        options.margin,
+    );
+    eprintln!(
+        "Generated {} positions in {:.1}s",
+        book.len(),
+        (now_ms() - start_ms) / 1000.0
+    );
+
+    println!("# Four-in-a-row opening book generated with fiar_book");
+    println!(
+        "# --plies {} --depth {} --margin {}",
+        options.plies, options.depth, options.margin
+    );
+    print!("{}", book.to_text());
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_options, Options};
+
+    #[test]
+    fn test_parse_options() {
+        let args: Vec<String> = ["--depth", "8", "--margin", "0"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        assert_eq!(
+            parse_options(&args).unwrap(),
+            Options {
+                plies: 6,
+                depth: 8,
+                margin: 0,
+            }
+        );
+        assert!(parse_options(&["--depth".to_owned()]).is_err());
+        assert!(parse_options(&["--plies".to_owned(), "many".to_owned()]).is_err());
+        assert!(parse_options(&["--margin".to_owned(), "-1".to_owned()]).is_err());
+        // Would wrap to 0 if parsed as usize and cast
+        assert_eq!(
+            parse_options(&["--margin".to_owned(), "4294967296".to_owned()]),
+            Err("Invalid value 4294967296 for --margin".to_owned())
+        );
+    }
+}
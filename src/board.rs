@@ -1,5 +1,7 @@
-use crate::{Cell, Coords, DeltaCoords, Error};
-use simple_error::bail;
+use crate::{
+    BoardMove, Cell, Coords, DeltaCoords, Error, GameOutcome, IllegalMoveReason, Symmetry,
+    WinningLines,
+};
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
@@ -8,6 +10,7 @@ pub struct Board {
     cells: Vec<Cell>,
     width: u32,
     height: u32,
+    hash: u64,
 }
 
 impl Board {
@@ -18,18 +21,468 @@ impl Board {
     pub fn get_cell(&self, row: u32, col: u32) -> Result<Cell, Error> {
         match self.in_bounds(row, col) {
             true => Ok(*self.cells.get(self.get_index(row, col)).unwrap()),
-            false => bail!("Out of bounds"),
+            false => Err(Error::OutOfBounds(Coords { row, col })),
         }
     }
 
     pub fn set_state(&mut self, state: Vec<Cell>) -> Result<(), Error> {
         if state.len() != (self.width * self.height) as usize {
-            bail!("State size does not match board size");
+            return Err(Error::InvalidPosition(
+                "State size does not match board size".to_owned(),
+            ));
         }
 
         self.cells = state;
+        self.hash = self
+            .cells
+            .iter()
+            .enumerate()
+            .fold(0, |hash, (idx, cell)| hash ^ cell.zobrist_key(idx));
         Ok(())
     }
+
+    /// Determine the outcome of the game after `last_move`.
+    ///
+    /// The winner is determined with `line_winner`, so the same assumptions
+    /// apply. Without winner, a full board is a draw.
+    pub fn outcome(&self, last_move: &Coords, num_winner: i32) -> GameOutcome {
+        match self.line_winner(last_move, num_winner) {
+            Cell::Empty if self.is_full() => GameOutcome::Draw,
+            Cell::Empty => GameOutcome::Ongoing,
+            side => GameOutcome::Win(side),
+        }
+    }
+
+    /// Check whether a piece can be placed on `coords`.
+    ///
+    /// With `gravity`, pieces have to land on the lowest empty cell of their
+    /// column.
+    pub fn validate_placement(
+        &self,
+        coords: &Coords,
+        gravity: bool,
+    ) -> Result<(), IllegalMoveReason> {
+        let Coords { row, col } = *coords;
+        if !self.in_bounds(row, col) {
+            return Err(IllegalMoveReason::OutOfBounds);
+        }
+        if gravity && self.cells[self.get_index(0, col)] != Cell::Empty {
+            return Err(IllegalMoveReason::ColumnFull);
+        }
+        if self.cells[self.get_index(row, col)] != Cell::Empty {
+            return Err(IllegalMoveReason::CellOccupied);
+        }
+        if gravity
+            && row + 1 < self.height
+            && self.cells[self.get_index(row + 1, col)] == Cell::Empty
+        {
+            return Err(IllegalMoveReason::Floating);
+        }
+
+        Ok(())
+    }
+
+    /// Winner and all winning lines through `last_move`, e.g. to highlight
+    /// them.
+    ///
+    /// Unlike `line_winner`, every direction is checked, so a move which
+    /// completes two lines at once reports both. A line holds all pieces of
+    /// the run, which may be longer than `num_winner`.
+    pub fn winning_lines(&self, last_move: &Coords, num_winner: i32) -> Option<WinningLines> {
+        let winner = self.get_cell(last_move.row, last_move.col).ok()?;
+        if winner == Cell::Empty {
+            return None;
+        }
+
+        let lines: Vec<Vec<Coords>> = LINE_DIRECTIONS
+            .iter()
+            .map(|d_pos| run_through(self, last_move, d_pos))
+            .filter(|line| line.len() as i32 >= num_winner)
+            .collect();
+        match lines.is_empty() {
+            true => None,
+            false => Some(WinningLines { winner, lines }),
+        }
+    }
+
+    /// Scan every row, column and diagonal for `num_winner` in a row.
+    ///
+    /// Unlike `line_winner`, this does not rely on the last move, e.g. for
+    /// boards filled with `set_state`. Fails if both sides have a line, which
+    /// cannot happen in a game.
+    pub fn scan_winner(&self, num_winner: i32) -> Result<Cell, Error> {
+        let mut has_line = [false; 2];
+        for (pos, d_pos) in self.lines() {
+            for run in self.line_cells(&pos, &d_pos).chunk_by(|a, b| a == b) {
+                if (run.len() as i32) < num_winner {
+                    continue;
+                }
+                match run[0] {
+                    Cell::X => has_line[0] = true,
+                    Cell::O => has_line[1] = true,
+                    Cell::Empty => (),
+                }
+            }
+        }
+
+        match has_line {
+            [true, true] => Err(Error::InvalidPosition(format!(
+                "Both X and O have {} in a row",
+                num_winner
+            ))),
+            [true, false] => Ok(Cell::X),
+            [false, true] => Ok(Cell::O),
+            [false, false] => Ok(Cell::Empty),
+        }
+    }
+
+    /// Check that any win on the board was completed by `last_move`, so
+    /// that `line_winner` detects it.
+    pub fn validate_winner(&self, last_move: &BoardMove, num_winner: i32) -> Result<(), Error> {
+        match self.scan_winner(num_winner)? {
+            Cell::Empty => Ok(()),
+            winner
+                if winner == last_move.side
+                    && self.line_winner(&last_move.coords, num_winner) == winner =>
+            {
+                Ok(())
+            }
+            winner => Err(Error::InvalidPosition(format!(
+                "{:?} has {} in a row which the last move did not complete",
+                winner, num_winner
+            ))),
+        }
+    }
+
+    /// Check that the board has the `height` and `width` which `game` is
+    /// played on.
+    pub fn validate_size(&self, height: u32, width: u32, game: &str) -> Result<(), Error> {
+        if (self.height, self.width) != (height, width) {
+            return Err(Error::InvalidPosition(format!(
+                "{} needs a {}x{} board instead of {}x{}",
+                game, height, width, self.height, self.width
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Start coordinates and directions of all horizontal, vertical and
+    /// diagonal lines on the board.
+    pub fn lines(&self) -> Vec<(Coords, DeltaCoords)> {
+        let mut lines = Vec::new();
+
+        for row in 0..self.height {
+            // Horizontal
+            lines.push((Coords { row, col: 0 }, DeltaCoords { row: 0, col: 1 }));
+            // Diagonals down and up starting in the first column
+            lines.push((Coords { row, col: 0 }, DeltaCoords { row: 1, col: 1 }));
+            lines.push((Coords { row, col: 0 }, DeltaCoords { row: -1, col: 1 }));
+        }
+
+        for col in 0..self.width {
+            // Vertical
+            lines.push((Coords { row: 0, col }, DeltaCoords { row: 1, col: 0 }));
+        }
+
+        for col in 1..self.width {
+            // Diagonals down and up starting in the first and last row
+            lines.push((Coords { row: 0, col }, DeltaCoords { row: 1, col: 1 }));
+            lines.push((
+                Coords {
+                    row: self.height - 1,
+                    col,
+                },
+                DeltaCoords { row: -1, col: 1 },
+            ));
+        }
+
+        lines
+    }
+
+    /// Cells on the line starting at `pos` in direction `d_pos` until the
+    /// border of the board.
+    pub fn line_cells(&self, pos: &Coords, d_pos: &DeltaCoords) -> Vec<Cell> {
+        let mut cells = Vec::new();
+
+        let Coords {
+            row: mut cur_row,
+            col: mut cur_col,
+        } = pos;
+
+        while self.in_bounds(cur_row, cur_col) {
+            cells.push(self.cells[self.get_index(cur_row, cur_col)]);
+
+            cur_row = (cur_row as i32 + d_pos.row) as u32;
+            cur_col = (cur_col as i32 + d_pos.col) as u32;
+        }
+
+        cells
+    }
+
+    /// Zobrist hash of the cells, updated incrementally with every set cell.
+    pub fn zobrist_hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Parse a board and the side to move from a FEN-like `notation`.
+    ///
+    /// Rows are listed from top to bottom and separated by `/`. Within a row,
+    /// `X` and `O` are pieces and a number counts consecutive empty cells.
+    /// The side to move follows after a space, e.g. `3/1X1/3 O`. Since X
+    /// always starts, X must have as many pieces as O or one more.
+    pub fn from_notation(notation: &str) -> Result<(Board, Cell), Error> {
+        let (rows, side_to_move) = match notation.trim().split_once(' ') {
+            Some((rows, side)) => (rows, side.trim()),
+            None => {
+                return Err(Error::InvalidPosition(
+                    "Expected the rows and the side to move separated by a space".to_owned(),
+                ))
+            }
+        };
+
+        let mut cells = Vec::new();
+        let mut width = None;
+        for (row, row_notation) in rows.split('/').enumerate() {
+            if row >= MAX_NOTATION_SIDE {
+                return Err(Error::InvalidPosition(format!(
+                    "More than {} rows",
+                    MAX_NOTATION_SIDE
+                )));
+            }
+
+            let mut row_cells = Vec::new();
+            let mut num_empty: usize = 0;
+            for c in row_notation.chars() {
+                if let Some(digit) = c.to_digit(10) {
+                    // Checked before anything is allocated for the cells
+                    num_empty = num_empty
+                        .checked_mul(10)
+                        .and_then(|num| num.checked_add(digit as usize))
+                        .filter(|&num| num <= MAX_NOTATION_SIDE)
+                        .ok_or_else(|| row_too_wide(row))?;
+                    continue;
+                }
+
+                let cell = match c {
+                    'X' | 'x' => Cell::X,
+                    'O' | 'o' => Cell::O,
+                    _ => {
+                        return Err(Error::InvalidPosition(format!(
+                            "Invalid character '{}' in row {}",
+                            c, row
+                        )))
+                    }
+                };
+                extend_row(&mut row_cells, num_empty, row)?;
+                num_empty = 0;
+                if row_cells.len() >= MAX_NOTATION_SIDE {
+                    return Err(row_too_wide(row));
+                }
+                row_cells.push(cell);
+            }
+            extend_row(&mut row_cells, num_empty, row)?;
+            if cells.len() + row_cells.len() > MAX_NOTATION_CELLS {
+                return Err(Error::InvalidPosition(format!(
+                    "More than {} cells",
+                    MAX_NOTATION_CELLS
+                )));
+            }
+
+            match width {
+                _ if row_cells.is_empty() => {
+                    return Err(Error::InvalidPosition(format!("Row {} has no cells", row)))
+                }
+                Some(width) if width != row_cells.len() => {
+                    return Err(Error::InvalidPosition(format!(
+                        "Row {} has {} cells instead of {}",
+                        row,
+                        row_cells.len(),
+                        width
+                    )))
+                }
+                _ => width = Some(row_cells.len()),
+            }
+            cells.append(&mut row_cells);
+        }
+
+        let side_to_move = match side_to_move {
+            "X" | "x" => Cell::X,
+            "O" | "o" => Cell::O,
+            _ => {
+                return Err(Error::InvalidPosition(format!(
+                    "Invalid side to move '{}'",
+                    side_to_move
+                )))
+            }
+        };
+
+        let num_x = cells.iter().filter(|&&cell| cell == Cell::X).count();
+        let num_o = cells.iter().filter(|&&cell| cell == Cell::O).count();
+        match side_to_move {
+            Cell::X if num_x == num_o => (),
+            Cell::O if num_x == num_o + 1 => (),
+            _ => {
+                return Err(Error::InvalidPosition(format!(
+                    "Impossible piece count of {} X and {} O with {:?} to move",
+                    num_x, num_o, side_to_move
+                )))
+            }
+        }
+
+        let width = width.expect("At least one row") as u32;
+        let mut board = Board::new(cells.len() as u32 / width, width);
+        board.set_state(cells)?;
+
+        Ok((board, side_to_move))
+    }
+
+    /// Format the board and `side_to_move` in the notation of `from_notation`.
+    pub fn to_notation(&self, side_to_move: Cell) -> String {
+        let rows: Vec<String> = self
+            .cells
+            .chunks(self.width as usize)
+            .map(|row| {
+                let mut row_notation = String::new();
+                let mut num_empty = 0;
+                for cell in row {
+                    match cell {
+                        Cell::Empty => {
+                            num_empty += 1;
+                            continue;
+                        }
+                        _ if num_empty > 0 => {
+                            row_notation += &num_empty.to_string();
+                            num_empty = 0;
+                        }
+                        _ => (),
+                    }
+                    row_notation.push(if *cell == Cell::X { 'X' } else { 'O' });
+                }
+                if num_empty > 0 {
+                    row_notation += &num_empty.to_string();
+                }
+                row_notation
+            })
+            .collect();
+
+        let side = match side_to_move {
+            Cell::O => "O",
+            _ => "X",
+        };
+        format!("{} {}", rows.join("/"), side)
+    }
+
+    /// Find a move of the side which did not move next which could have led
+    /// to this board, e.g. after parsing it with `from_notation`.
+    ///
+    /// A move which completed a line of `num_winner` is preferred so that the
+    /// outcome of the position is detected with `line_winner`. With
+    /// `gravity`, pieces must lie on top of each other and only the top piece
+    /// of a column can have been the last move.
+    pub fn infer_last_move(
+        &self,
+        side_to_move: Cell,
+        num_winner: i32,
+        gravity: bool,
+    ) -> Result<BoardMove, Error> {
+        let last_side = match side_to_move {
+            Cell::X => Cell::O,
+            _ => Cell::X,
+        };
+
+        let mut candidates = Vec::new();
+        for (idx, &cell) in self.cells.iter().enumerate() {
+            let Coords { row, col } = self.get_coords(idx);
+            let below = self.get_cell(row + 1, col).unwrap_or(Cell::X);
+            let above = match row {
+                0 => Cell::Empty,
+                _ => self.get_cell(row - 1, col).unwrap_or(Cell::Empty),
+            };
+
+            if gravity && cell != Cell::Empty && below == Cell::Empty {
+                return Err(Error::InvalidPosition(format!(
+                    "Piece at row {} and column {} is floating",
+                    row, col
+                )));
+            }
+            if cell == last_side && (!gravity || above == Cell::Empty) {
+                candidates.push(Coords { row, col });
+            }
+        }
+
+        if self.cells.iter().all(|&cell| cell == Cell::Empty) {
+            // We usually start with X, so the "last" was O
+            return Ok(BoardMove::new(0, 0, Cell::O));
+        }
+
+        match candidates
+            .iter()
+            .find(|coords| self.line_winner(coords, num_winner) == last_side)
+            .or_else(|| candidates.first())
+        {
+            Some(coords) => Ok(BoardMove {
+                coords: *coords,
+                side: last_side,
+            }),
+            None => Err(Error::InvalidPosition(format!(
+                "No piece of {:?} can have been the last move",
+                last_side
+            ))),
+        }
+    }
+
+    /// Symmetries which keep the rules of a game on this board.
+    ///
+    /// With `gravity`, only mirroring the columns keeps the pieces at the
+    /// bottom. Rotating by 90 degrees requires a square board.
+    pub fn symmetries(&self, gravity: bool) -> Vec<Symmetry> {
+        Symmetry::ALL
+            .iter()
+            .copied()
+            .filter(|symmetry| match gravity {
+                true => matches!(symmetry, Symmetry::Identity | Symmetry::MirrorColumns),
+                false => self.height == self.width || !symmetry.swaps_axes(),
+            })
+            .collect()
+    }
+
+    /// Board with every cell moved by `symmetry`.
+    pub fn transformed(&self, symmetry: Symmetry) -> Board {
+        let mut board = match symmetry.swaps_axes() {
+            true => Board::new(self.width, self.height),
+            false => Board::new(self.height, self.width),
+        };
+
+        for (idx, &cell) in self.cells.iter().enumerate() {
+            if cell != Cell::Empty {
+                let Coords { row, col } =
+                    symmetry.map_coords(&self.get_coords(idx), self.height, self.width);
+                board.set_cell(row, col, cell);
+            }
+        }
+
+        board
+    }
+
+    /// Representative of all boards which are symmetric to this one.
+    ///
+    /// Out of the boards transformed by `symmetries`, the one with the
+    /// smallest cells in row-major order is picked. Returns it together with
+    /// the symmetry which leads to it.
+    pub fn canonical(&self, symmetries: &[Symmetry]) -> (Board, Symmetry) {
+        symmetries
+            .iter()
+            .map(|&symmetry| (self.transformed(symmetry), symmetry))
+            .min_by(|(board, _), (other, _)| {
+                board
+                    .cells
+                    .iter()
+                    .map(|&cell| cell as u8)
+                    .cmp(other.cells.iter().map(|&cell| cell as u8))
+            })
+            .unwrap_or((self.clone(), Symmetry::Identity))
+    }
 }
 
 #[wasm_bindgen]
@@ -39,6 +492,7 @@ impl Board {
             cells: vec![Cell::Empty; (width * height) as usize],
             width,
             height,
+            hash: 0,
         }
     }
 
@@ -65,9 +519,38 @@ impl Board {
 
         let cell_idx = self.get_index(row, col);
         self.cells[cell_idx] = mark;
+        self.hash ^= mark.zobrist_key(cell_idx);
         true
     }
 
+    /// Take back `board_move` by clearing its cell.
+    ///
+    /// Fails if the cell does not hold a piece of the side of the move.
+    pub fn undo_move(&mut self, board_move: &BoardMove) -> bool {
+        let Coords { row, col } = board_move.coords;
+        if !self.in_bounds(row, col) || board_move.side == Cell::Empty {
+            return false;
+        }
+
+        let cell_idx = self.get_index(row, col);
+        if self.cells[cell_idx] != board_move.side {
+            return false;
+        }
+
+        self.cells[cell_idx] = Cell::Empty;
+        self.hash ^= board_move.side.zobrist_key(cell_idx);
+        true
+    }
+
+    /// Play `board_move` again after it was taken back with `undo_move`.
+    pub fn redo_move(&mut self, board_move: &BoardMove) -> bool {
+        self.set_cell(
+            board_move.coords.row,
+            board_move.coords.col,
+            board_move.side,
+        )
+    }
+
     pub fn get_index(&self, row: u32, col: u32) -> usize {
         let idx = row * self.width + col;
         idx as usize
@@ -89,6 +572,7 @@ impl Board {
         for cell in self.cells.iter_mut() {
             *cell = Cell::Empty;
         }
+        self.hash = 0;
     }
 
     /// Determine the winner on the lines through `self.last_move`.
@@ -96,8 +580,14 @@ impl Board {
     /// This assumes that there is no winning pattern on any other line which
     /// does not go through `self.last_move`. This is a reasonable assumption
     /// if every game state is evaluated directly, thus a previously completed
-    /// pattern on another line would have been detected before.
+    /// pattern on another line would have been detected before. Imported
+    /// positions are checked with `scan_winner` instead.
     pub fn line_winner(&self, last_move: &Coords, num_winner: i32) -> Cell {
+        // E.g. the placeholder move of a new game, which did not place a piece
+        if let Ok(Cell::Empty) = self.get_cell(last_move.row, last_move.col) {
+            return Cell::Empty;
+        }
+
         // To determine the potential winner, we check the horizontal, vertial,
         // diagonal-down and diagonal-up lines through `self.last_move`.
 
@@ -167,15 +657,108 @@ impl Board {
         Cell::Empty
     }
 
-    pub fn first_empty_in_column(&self, col: u32) -> Coords {
-        for row in (0..self.height()).rev() {
-            if let Ok(Cell::Empty) = self.get_cell(row, col) {
-                return Coords { row, col };
-            }
+    /// Winning lines through `last_move` as `{ winner, lines }` object, or
+    /// `null` if the move did not win.
+    #[wasm_bindgen(js_name = winning_lines)]
+    pub fn winning_lines_js(
+        &self,
+        last_move: &Coords,
+        num_winner: i32,
+    ) -> Result<JsValue, JsValue> {
+        Ok(WinningLines::option_to_js_value(
+            &self.winning_lines(last_move, num_winner),
+        )?)
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.cells.iter().all(|&cell| cell != Cell::Empty)
+    }
+
+    /// Outcome of the game after `last_move` as `{ kind, winner }` object.
+    #[wasm_bindgen(js_name = outcome)]
+    pub fn outcome_js(&self, last_move: &Coords, num_winner: i32) -> Result<JsValue, JsValue> {
+        Ok(self.outcome(last_move, num_winner).to_js_value()?)
+    }
+
+    /// Lowest empty cell of `col`, `None` if the column is full or missing.
+    pub fn first_empty_in_column(&self, col: u32) -> Option<Coords> {
+        (0..self.height())
+            .rev()
+            .map(|row| Coords { row, col })
+            .find(|coords| self.get_cell(coords.row, coords.col) == Ok(Cell::Empty))
+    }
+}
+
+impl std::fmt::Display for Board {
+    /// One line per row with `X`, `O` and `.` for empty cells.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for row in self.cells.chunks(self.width as usize) {
+            let line: Vec<&str> = row
+                .iter()
+                .map(|cell| match cell {
+                    Cell::X => "X",
+                    Cell::O => "O",
+                    Cell::Empty => ".",
+                })
+                .collect();
+            writeln!(f, "{}", line.join(" "))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Most rows or columns of a board in the notation of `from_notation`
+const MAX_NOTATION_SIDE: usize = 64;
+/// Most cells of a board in the notation of `from_notation`
+const MAX_NOTATION_CELLS: usize = 1024;
+
+fn row_too_wide(row: usize) -> Error {
+    Error::InvalidPosition(format!(
+        "Row {} has more than {} cells",
+        row, MAX_NOTATION_SIDE
+    ))
+}
+
+/// Append `num_empty` empty cells to a row of the notation.
+fn extend_row(row_cells: &mut Vec<Cell>, num_empty: usize, row: usize) -> Result<(), Error> {
+    if row_cells.len() + num_empty > MAX_NOTATION_SIDE {
+        return Err(row_too_wide(row));
+    }
+
+    row_cells.resize(row_cells.len() + num_empty, Cell::Empty);
+    Ok(())
+}
+
+/// Horizontal, vertical, diagonal down and diagonal up
+const LINE_DIRECTIONS: [DeltaCoords; 4] = [
+    DeltaCoords { row: 0, col: 1 },
+    DeltaCoords { row: 1, col: 0 },
+    DeltaCoords { row: 1, col: 1 },
+    DeltaCoords { row: -1, col: 1 },
+];
+
+/// Cells with the same piece as `pos` on its line in direction `d_pos`,
+/// ordered along the direction.
+fn run_through(board: &Board, pos: &Coords, d_pos: &DeltaCoords) -> Vec<Coords> {
+    let side = board.get_cell(pos.row, pos.col).unwrap();
+    // Coordinates left of or above the board wrap around and are out of bounds
+    let step = |coords: &Coords, sign: i32| {
+        let next = Coords {
+            row: (coords.row as i32 + sign * d_pos.row) as u32,
+            col: (coords.col as i32 + sign * d_pos.col) as u32,
+        };
+        match board.get_cell(next.row, next.col) {
+            Ok(cell) if cell == side => Some(next),
+            _ => None,
         }
+    };
 
-        Coords { row: 0, col }
+    let mut start = *pos;
+    while let Some(prev) = step(&start, -1) {
+        start = prev;
     }
+    std::iter::successors(Some(start), |coords| step(coords, 1)).collect()
 }
 
 fn side_with_min_equal(board: &Board, pos: &Coords, d_pos: &DeltaCoords, num_winner: i32) -> Cell {
@@ -208,16 +791,16 @@ fn side_with_min_equal(board: &Board, pos: &Coords, d_pos: &DeltaCoords, num_win
 }
 
 #[cfg(test)]
-
 mod test {
 
-    use super::{Board, Cell, Coords};
+    use super::{Board, BoardMove, Cell, Coords, GameOutcome, Symmetry};
+    use crate::{Error, IllegalMoveReason};
 
     #[test]
     fn test_get_coords() {
         let board = Board::new(3, 4);
 
-        for (idx, coords) in vec![
+        for (idx, coords) in [
             (0, Coords { row: 0, col: 0 }),
             (1, Coords { row: 0, col: 1 }),
             (6, Coords { row: 1, col: 2 }),
@@ -248,4 +831,361 @@ mod test {
 
         assert_eq!(b1.line_winner(&last_move_coords, 3), Cell::X);
     }
+
+    #[test]
+    fn test_scan_winner() {
+        // The win is far away from any move which `line_winner` would check
+        let mut board = Board::new(4, 4);
+        for col in 1..4 {
+            board.set_cell(3, col, Cell::O);
+        }
+        assert_eq!(
+            board.line_winner(&Coords { row: 0, col: 0 }, 3),
+            Cell::Empty
+        );
+        assert_eq!(board.scan_winner(3), Ok(Cell::O));
+        assert_eq!(board.scan_winner(4), Ok(Cell::Empty));
+
+        // Diagonal up
+        let (board, _) = Board::from_notation("2X1/1XO1/XO2/4 O").unwrap();
+        assert_eq!(board.scan_winner(3), Ok(Cell::X));
+
+        let (board, _) = Board::from_notation("XXX/OOO/X2 O").unwrap();
+        assert!(matches!(
+            board.scan_winner(3),
+            Err(Error::InvalidPosition(message)) if message.contains("Both")
+        ));
+        assert!(board
+            .validate_winner(&BoardMove::new(2, 0, Cell::X), 3)
+            .is_err());
+
+        // Only the last move may complete a line
+        let (board, _) = Board::from_notation("XXX/OO1/3 O").unwrap();
+        assert_eq!(
+            board.validate_winner(&BoardMove::new(0, 1, Cell::X), 3),
+            Ok(())
+        );
+        assert!(board
+            .validate_winner(&BoardMove::new(1, 1, Cell::O), 3)
+            .is_err());
+    }
+
+    #[test]
+    fn test_winning_lines() {
+        // The last move at the top left completes a row and a column
+        let (board, _) = Board::from_notation("XXX/XOO/XOO O").unwrap();
+        let winning = board.winning_lines(&Coords { row: 0, col: 0 }, 3).unwrap();
+        assert_eq!(winning.winner, Cell::X);
+        let coords = |cells: [(u32, u32); 3]| cells.map(|(row, col)| Coords { row, col }).to_vec();
+        assert_eq!(
+            winning.lines,
+            vec![
+                coords([(0, 0), (0, 1), (0, 2)]),
+                coords([(0, 0), (1, 0), (2, 0)])
+            ]
+        );
+        assert_eq!(board.winning_lines(&Coords { row: 1, col: 2 }, 3), None);
+
+        // Runs longer than needed are reported completely
+        let (board, _) = Board::from_notation("7/7/7/7/OOOO3/XXXXX2 O").unwrap();
+        let winning = board.winning_lines(&Coords { row: 5, col: 2 }, 4).unwrap();
+        assert_eq!(winning.lines.len(), 1);
+        assert_eq!(winning.lines[0].len(), 5);
+        assert_eq!(winning.lines[0][0], Coords { row: 5, col: 0 });
+
+        // Diagonal up from the bottom left, empty cells never win
+        let (board, _) = Board::from_notation("3/1X1/XO1 O").unwrap();
+        let winning = board.winning_lines(&Coords { row: 1, col: 1 }, 2).unwrap();
+        assert_eq!(
+            winning.lines,
+            vec![vec![Coords { row: 2, col: 0 }, Coords { row: 1, col: 1 }]]
+        );
+        assert_eq!(board.winning_lines(&Coords { row: 0, col: 0 }, 1), None);
+    }
+
+    #[test]
+    fn test_outcome() {
+        let mut b1 = Board::new(3, 3);
+        b1.set_cell(1, 1, Cell::X);
+        assert_eq!(
+            b1.outcome(&Coords { row: 1, col: 1 }, 3),
+            GameOutcome::Ongoing
+        );
+
+        // XOX
+        // XOO
+        // OXX
+        let _ = b1.set_state(vec![
+            Cell::X,
+            Cell::O,
+            Cell::X,
+            Cell::X,
+            Cell::O,
+            Cell::O,
+            Cell::O,
+            Cell::X,
+            Cell::X,
+        ]);
+        assert!(b1.is_full());
+        assert_eq!(b1.outcome(&Coords { row: 2, col: 2 }, 3), GameOutcome::Draw);
+
+        // A win on the last move of a full board is not a draw
+        b1.reset();
+        let _ = b1.set_state(vec![
+            Cell::X,
+            Cell::O,
+            Cell::X,
+            Cell::O,
+            Cell::X,
+            Cell::O,
+            Cell::O,
+            Cell::X,
+            Cell::X,
+        ]);
+        assert_eq!(
+            b1.outcome(&Coords { row: 2, col: 2 }, 3),
+            GameOutcome::Win(Cell::X)
+        );
+    }
+
+    #[test]
+    fn test_lines() {
+        let board = Board::new(3, 4);
+        let lines = board.lines();
+
+        // 3 rows, 4 columns and 6 diagonals in each direction
+        assert_eq!(lines.len(), 3 + 4 + 2 * 6);
+
+        // Every cell is on exactly one line per direction
+        let num_cells: usize = lines
+            .iter()
+            .map(|(pos, d_pos)| board.line_cells(pos, d_pos).len())
+            .sum();
+        assert_eq!(num_cells, 4 * 12);
+    }
+
+    #[test]
+    fn test_zobrist_hash() {
+        let mut b1 = Board::new(3, 3);
+        b1.set_cell(0, 0, Cell::X);
+        b1.set_cell(1, 1, Cell::O);
+
+        // Same position reached by a different move order
+        let mut b2 = Board::new(3, 3);
+        b2.set_cell(1, 1, Cell::O);
+        b2.set_cell(0, 0, Cell::X);
+        assert_eq!(b1.zobrist_hash(), b2.zobrist_hash());
+
+        // Setting the full state yields the same hash as setting cells
+        let mut b3 = Board::new(3, 3);
+        let _ = b3.set_state(b1.cells().clone());
+        assert_eq!(b1.zobrist_hash(), b3.zobrist_hash());
+
+        // Swapped markers are a different position
+        let mut b4 = Board::new(3, 3);
+        b4.set_cell(0, 0, Cell::O);
+        b4.set_cell(1, 1, Cell::X);
+        assert_ne!(b1.zobrist_hash(), b4.zobrist_hash());
+
+        b1.reset();
+        assert_eq!(b1.zobrist_hash(), Board::new(3, 3).zobrist_hash());
+    }
+
+    #[test]
+    fn test_undo_redo_move() {
+        let mut board = Board::new(3, 3);
+        let empty_hash = board.zobrist_hash();
+
+        let first = BoardMove::new(1, 1, Cell::X);
+        let second = BoardMove::new(0, 2, Cell::O);
+        assert!(board.redo_move(&first));
+        assert!(board.redo_move(&second));
+        let hash = board.zobrist_hash();
+
+        // Only the piece of the move can be taken back
+        assert!(!board.undo_move(&BoardMove::new(1, 1, Cell::O)));
+        assert!(!board.undo_move(&BoardMove::new(2, 2, Cell::X)));
+        assert!(!board.undo_move(&BoardMove::new(3, 0, Cell::X)));
+
+        assert!(board.undo_move(&second));
+        assert!(board.undo_move(&first));
+        assert_eq!(board, Board::new(3, 3));
+        assert_eq!(board.zobrist_hash(), empty_hash);
+
+        // A move cannot be redone on an occupied cell
+        assert!(board.redo_move(&first));
+        assert!(!board.redo_move(&BoardMove::new(1, 1, Cell::O)));
+        assert!(board.redo_move(&second));
+        assert_eq!(board.zobrist_hash(), hash);
+    }
+
+    #[test]
+    fn test_validate_placement() {
+        let (board, _) = Board::from_notation("X2/O2/X1O X").unwrap();
+        let validate = |row, col, gravity| board.validate_placement(&Coords { row, col }, gravity);
+
+        assert_eq!(validate(0, 1, false), Ok(()));
+        assert_eq!(validate(1, 0, false), Err(IllegalMoveReason::CellOccupied));
+        assert_eq!(validate(3, 0, false), Err(IllegalMoveReason::OutOfBounds));
+
+        // With gravity, only the lowest empty cell of a column is allowed
+        assert_eq!(validate(2, 1, true), Ok(()));
+        assert_eq!(validate(1, 2, true), Ok(()));
+        assert_eq!(validate(0, 2, true), Err(IllegalMoveReason::Floating));
+        assert_eq!(validate(2, 2, true), Err(IllegalMoveReason::CellOccupied));
+        assert_eq!(validate(1, 0, true), Err(IllegalMoveReason::ColumnFull));
+    }
+
+    #[test]
+    fn test_first_empty_in_column() {
+        let (board, _) = Board::from_notation("X2/O2/X1O X").unwrap();
+        assert_eq!(board.first_empty_in_column(0), None);
+        assert_eq!(
+            board.first_empty_in_column(1),
+            Some(Coords { row: 2, col: 1 })
+        );
+        assert_eq!(
+            board.first_empty_in_column(2),
+            Some(Coords { row: 1, col: 2 })
+        );
+        assert_eq!(board.first_empty_in_column(3), None);
+    }
+
+    #[test]
+    fn test_display() {
+        let mut board = Board::new(2, 3);
+        board.set_cell(0, 1, Cell::O);
+        board.set_cell(1, 2, Cell::X);
+
+        assert_eq!(board.to_string(), ". O .\n. . X\n");
+    }
+
+    #[test]
+    fn test_notation() {
+        let mut board = Board::new(3, 4);
+        board.set_cell(0, 1, Cell::O);
+        board.set_cell(1, 3, Cell::X);
+        board.set_cell(2, 0, Cell::X);
+
+        let notation = board.to_notation(Cell::O);
+        assert_eq!(notation, "1O2/3X/X3 O");
+        assert_eq!(Board::from_notation(&notation).unwrap(), (board, Cell::O));
+        assert_eq!(Board::new(3, 12).to_notation(Cell::X), "12/12/12 X");
+        assert_eq!(
+            Board::from_notation("12/12/12 x").unwrap(),
+            (Board::new(3, 12), Cell::X)
+        );
+
+        for invalid in [
+            // Missing side to move
+            "3/3/3",
+            // Rows of different size
+            "3/4/3 X",
+            "3//3 X",
+            // Bad characters
+            "3/1Y1/3 X",
+            "3/3/3 Y",
+            // Impossible piece counts
+            "3/1O1/3 X",
+            "X2/1X1/3 O",
+            "3/1X1/3 X",
+        ] {
+            assert!(Board::from_notation(invalid).is_err(), "{}", invalid);
+        }
+
+        // Huge sizes are rejected before allocating the cells
+        let too_many_rows = vec!["1"; 65].join("/") + " X";
+        let too_many_cells = vec!["64"; 17].join("/") + " X";
+        for huge in [
+            "99999999999999999999X 1 X",
+            "9999999999 X",
+            "65 X",
+            "64X X",
+            &too_many_rows,
+            &too_many_cells,
+        ] {
+            assert!(
+                matches!(Board::from_notation(huge), Err(Error::InvalidPosition(_))),
+                "{}",
+                huge
+            );
+        }
+        let (board, _) = Board::from_notation(&(vec!["64"; 16].join("/") + " X")).unwrap();
+        assert_eq!((board.height(), board.width()), (16, 64));
+    }
+
+    #[test]
+    fn test_infer_last_move() {
+        // A completed line is found as last move
+        let (board, side_to_move) = Board::from_notation("XO1/XO1/X2 O").unwrap();
+        assert_eq!(
+            board.infer_last_move(side_to_move, 3, false).unwrap(),
+            BoardMove::new(0, 0, Cell::X)
+        );
+
+        // With gravity, only the top pieces can have been the last move
+        let (board, side_to_move) = Board::from_notation("3/O2/X1X O").unwrap();
+        assert_eq!(
+            board.infer_last_move(side_to_move, 3, true).unwrap(),
+            BoardMove::new(2, 2, Cell::X)
+        );
+        let (board, side_to_move) = Board::from_notation("3/X2/O1X O").unwrap();
+        assert_eq!(
+            board.infer_last_move(side_to_move, 3, true).unwrap(),
+            BoardMove::new(1, 0, Cell::X)
+        );
+
+        // Floating pieces are impossible with gravity
+        let (board, side_to_move) = Board::from_notation("3/X2/2O X").unwrap();
+        assert!(board.infer_last_move(side_to_move, 3, false).is_ok());
+        assert!(board.infer_last_move(side_to_move, 3, true).is_err());
+
+        let (board, side_to_move) = Board::from_notation("3/3/3 X").unwrap();
+        assert_eq!(
+            board.infer_last_move(side_to_move, 3, true).unwrap(),
+            BoardMove::new(0, 0, Cell::O)
+        );
+    }
+
+    #[test]
+    fn test_symmetries() {
+        // XO
+        // X
+        let (board, _) = Board::from_notation("XO1/X2/3 O").unwrap();
+
+        let rotated = board.transformed(Symmetry::Rotate90);
+        assert_eq!(rotated.to_notation(Cell::O), "1XX/2O/3 O");
+        let mut turned = board.clone();
+        for _ in 0..4 {
+            turned = turned.transformed(Symmetry::Rotate90);
+        }
+        assert_eq!(turned, board);
+        for symmetry in Symmetry::ALL {
+            assert_eq!(
+                board.transformed(symmetry).transformed(symmetry.inverse()),
+                board
+            );
+        }
+
+        // All symmetric boards share the canonical form
+        let (canonical, _) = board.canonical(&Symmetry::ALL);
+        for symmetry in Symmetry::ALL {
+            let (other, to_canonical) = board.transformed(symmetry).canonical(&Symmetry::ALL);
+            assert_eq!(other, canonical);
+            assert_eq!(
+                board.transformed(symmetry).transformed(to_canonical),
+                canonical
+            );
+        }
+
+        // Rotations by 90 degrees swap the size
+        let wide = Board::new(3, 4);
+        assert_eq!(wide.transformed(Symmetry::Transpose).height(), 4);
+        assert_eq!(wide.symmetries(false).len(), 4);
+        assert_eq!(board.symmetries(false).len(), 8);
+        assert_eq!(
+            Board::new(6, 7).symmetries(true),
+            vec![Symmetry::Identity, Symmetry::MirrorColumns]
+        );
+    }
 }
@@ -1,15 +1,70 @@
+use crate::transposition::zobrist_key;
+use crate::Error;
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
 #[repr(u8)]
-#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub enum Cell {
     Empty,
     X,
     O,
 }
 
+impl Cell {
+    /// Key of this marker at board index `idx` for Zobrist hashing.
+    ///
+    /// Empty cells do not contribute to the hash.
+    pub fn zobrist_key(&self, idx: usize) -> u64 {
+        match self {
+            Cell::Empty => 0,
+            Cell::X => zobrist_key(idx, 0),
+            Cell::O => zobrist_key(idx, 1),
+        }
+    }
+}
+
+/// State of a game after a move.
+///
+/// Serialized as `{ kind: "Win", winner: "X" }` for JS.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(tag = "kind", content = "winner")]
+pub enum GameOutcome {
+    Ongoing,
+    Win(Cell),
+    Draw,
+}
+
+impl GameOutcome {
+    pub fn is_over(&self) -> bool {
+        *self != GameOutcome::Ongoing
+    }
+
+    pub fn to_js_value(&self) -> Result<JsValue, Error> {
+        Ok(serde_wasm_bindgen::to_value(&self)?)
+    }
+}
+
+/// Winner of a game with the cells of every line which won it.
+///
+/// Serialized as `{ winner: "X", lines: [[{ row, col }, ...]] }` for JS.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct WinningLines {
+    pub winner: Cell,
+    pub lines: Vec<Vec<Coords>>,
+}
+
+impl WinningLines {
+    /// `null` for JS if nobody won, otherwise the serialized lines.
+    pub fn option_to_js_value(lines: &Option<WinningLines>) -> Result<JsValue, Error> {
+        match lines {
+            Some(lines) => Ok(serde_wasm_bindgen::to_value(lines)?),
+            None => Ok(JsValue::NULL),
+        }
+    }
+}
+
 #[wasm_bindgen]
 #[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct Coords {
@@ -46,11 +101,90 @@ impl BoardMove {
         }
     }
 
-    pub fn from_js_value(js_value: JsValue) -> Self {
-        serde_wasm_bindgen::from_value(js_value).unwrap()
+    /// Move from a `{ coords: { row, col }, side }` object.
+    pub fn from_js_value(js_value: JsValue) -> Result<BoardMove, Error> {
+        Ok(serde_wasm_bindgen::from_value(js_value)?)
     }
 
-    pub fn to_js_value(&self) -> JsValue {
-        serde_wasm_bindgen::to_value(&self).unwrap()
+    pub fn to_js_value(&self) -> Result<JsValue, Error> {
+        Ok(serde_wasm_bindgen::to_value(&self)?)
+    }
+}
+
+/// Rotation or reflection of a board.
+///
+/// Rotations are clockwise. Symmetries which swap rows and columns turn a
+/// board of `height` x `width` cells into one of `width` x `height` cells.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Symmetry {
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    /// Swap the left and the right columns
+    MirrorColumns,
+    /// Swap the top and the bottom rows
+    MirrorRows,
+    /// Reflect at the diagonal from the top left
+    Transpose,
+    /// Reflect at the diagonal from the top right
+    AntiTranspose,
+}
+
+impl Symmetry {
+    pub const ALL: [Symmetry; 8] = [
+        Symmetry::Identity,
+        Symmetry::Rotate90,
+        Symmetry::Rotate180,
+        Symmetry::Rotate270,
+        Symmetry::MirrorColumns,
+        Symmetry::MirrorRows,
+        Symmetry::Transpose,
+        Symmetry::AntiTranspose,
+    ];
+
+    /// Symmetry which undoes this one.
+    pub fn inverse(&self) -> Symmetry {
+        match self {
+            Symmetry::Rotate90 => Symmetry::Rotate270,
+            Symmetry::Rotate270 => Symmetry::Rotate90,
+            other => *other,
+        }
+    }
+
+    /// Whether rows and columns are swapped.
+    pub fn swaps_axes(&self) -> bool {
+        matches!(
+            self,
+            Symmetry::Rotate90
+                | Symmetry::Rotate270
+                | Symmetry::Transpose
+                | Symmetry::AntiTranspose
+        )
+    }
+
+    /// Where `coords` on a board of `height` x `width` cells end up.
+    pub fn map_coords(&self, coords: &Coords, height: u32, width: u32) -> Coords {
+        let Coords { row, col } = *coords;
+        let (row, col) = match self {
+            Symmetry::Identity => (row, col),
+            Symmetry::Rotate90 => (col, height - 1 - row),
+            Symmetry::Rotate180 => (height - 1 - row, width - 1 - col),
+            Symmetry::Rotate270 => (width - 1 - col, row),
+            Symmetry::MirrorColumns => (row, width - 1 - col),
+            Symmetry::MirrorRows => (height - 1 - row, col),
+            Symmetry::Transpose => (col, row),
+            Symmetry::AntiTranspose => (width - 1 - col, height - 1 - row),
+        };
+
+        Coords { row, col }
+    }
+
+    /// Move of the same side to the mapped cell.
+    pub fn map_move(&self, board_move: &BoardMove, height: u32, width: u32) -> BoardMove {
+        BoardMove {
+            coords: self.map_coords(&board_move.coords, height, width),
+            side: board_move.side,
+        }
     }
 }
@@ -0,0 +1,415 @@
+use crate::transposition::SIDE_KEY;
+use crate::{
+    Board, BoardMove, Cell, Coords, Error, GameKind, GameOutcome, GameRecord, GameState, Heuristic,
+    IllegalMoveReason, Symmetry, WinningLines, X_WIN_VALUE,
+};
+use wasm_bindgen::prelude::*;
+
+/// State of an (m,n,k)-game: `num_winner` in a row wins on a board of any
+/// size.
+///
+/// With `gravity`, pieces drop to the lowest empty cell of a column like in
+/// Four-in-a-row. Without it, pieces can be placed on any empty cell like in
+/// tic-tac-toe or gomoku.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct ConnectNGameState {
+    board: Board,
+    last_move: BoardMove,
+    num_winner: i32,
+    gravity: bool,
+}
+
+#[wasm_bindgen]
+impl ConnectNGameState {
+    /// State after `last_move` on `board`, which is not validated, see
+    /// `try_new`.
+    pub fn new(board: Board, last_move: BoardMove, num_winner: i32, gravity: bool) -> Self {
+        Self {
+            board,
+            last_move,
+            num_winner,
+            gravity,
+        }
+    }
+
+    /// State after `last_move` on an imported `board`, e.g. filled with
+    /// `Board::set_state`.
+    ///
+    /// Fails if `num_winner` in a row cannot fit on the board or a win on
+    /// the board was not completed by `last_move`.
+    pub fn try_new(
+        board: Board,
+        last_move: BoardMove,
+        num_winner: i32,
+        gravity: bool,
+    ) -> Result<ConnectNGameState, Error> {
+        validate_rules(board.height(), board.width(), num_winner)?;
+        board.validate_winner(&last_move, num_winner)?;
+        Ok(Self::new(board, last_move, num_winner, gravity))
+    }
+
+    /// Empty board of `height` x `width` cells where X moves first.
+    ///
+    /// Fails if the board is empty or `num_winner` in a row cannot fit on it.
+    pub fn new_game(
+        height: u32,
+        width: u32,
+        num_winner: i32,
+        gravity: bool,
+    ) -> Result<ConnectNGameState, Error> {
+        validate_rules(height, width, num_winner)?;
+        Ok(Self::new(
+            Board::new(height, width),
+            BoardMove {
+                coords: Coords { row: 0, col: 0 },
+                // We usually start with X, so the "last" was O
+                side: Cell::O,
+            },
+            num_winner,
+            gravity,
+        ))
+    }
+
+    pub fn side(&self) -> Cell {
+        self.last_move.side
+    }
+
+    pub fn last_move(&self) -> BoardMove {
+        self.last_move
+    }
+
+    pub fn num_winner(&self) -> i32 {
+        self.num_winner
+    }
+
+    pub fn gravity(&self) -> bool {
+        self.gravity
+    }
+
+    /// Outcome of the game as `{ kind, winner }` object.
+    #[wasm_bindgen(js_name = outcome)]
+    pub fn outcome_js(&self) -> Result<JsValue, JsValue> {
+        Ok(GameState::outcome(self).to_js_value()?)
+    }
+
+    /// Lines won by the last move as `{ winner, lines }` object, or `null`.
+    #[wasm_bindgen(js_name = winning_lines)]
+    pub fn winning_lines_js(&self) -> Result<JsValue, JsValue> {
+        Ok(WinningLines::option_to_js_value(&self.winning_lines())?)
+    }
+
+    /// Load a position, the size of the board is taken from `notation`.
+    #[wasm_bindgen(js_name = from_notation)]
+    pub fn from_notation_js(
+        notation: &str,
+        num_winner: i32,
+        gravity: bool,
+    ) -> Result<ConnectNGameState, JsValue> {
+        Self::from_notation(notation, num_winner, gravity).map_err(JsValue::from)
+    }
+
+    /// Board and side to move in the notation of `Board::from_notation`.
+    pub fn to_notation(&self) -> String {
+        let side_to_move = match self.last_move.side {
+            Cell::X => Cell::O,
+            _ => Cell::X,
+        };
+        self.board.to_notation(side_to_move)
+    }
+}
+
+impl Default for ConnectNGameState {
+    /// Gomoku - five in a row on a 15x15 board.
+    fn default() -> Self {
+        Self::new_game(15, 15, 5, false).expect("Gomoku has valid rules")
+    }
+}
+
+impl ConnectNGameState {
+    /// Winner and every line completed by the last move.
+    pub fn winning_lines(&self) -> Option<WinningLines> {
+        self.board
+            .winning_lines(&self.last_move.coords, self.num_winner)
+    }
+
+    pub fn from_notation(notation: &str, num_winner: i32, gravity: bool) -> Result<Self, Error> {
+        let (board, side_to_move) = Board::from_notation(notation)?;
+        let last_move = board.infer_last_move(side_to_move, num_winner, gravity)?;
+        Self::try_new(board, last_move, num_winner, gravity)
+    }
+
+    /// Empty record of a game which starts at this state.
+    pub fn new_record(&self) -> GameRecord {
+        GameRecord::new(
+            GameKind::ConnectN,
+            self.board.height(),
+            self.board.width(),
+            self.num_winner,
+            self.gravity,
+            self.to_notation(),
+        )
+    }
+
+    /// Start state of `record` with the rules of the record.
+    pub fn from_record_start(record: &GameRecord) -> Result<Self, Error> {
+        if record.kind != GameKind::ConnectN {
+            return Err(Error::InvalidRecord(format!(
+                "Record of {:?} is not an (m,n,k)-game",
+                record.kind
+            )));
+        }
+
+        match &record.start {
+            Some(start) => Self::from_notation(start, record.num_winner, record.gravity),
+            None => Self::new_game(
+                record.height,
+                record.width,
+                record.num_winner,
+                record.gravity,
+            ),
+        }
+    }
+
+    fn child_state(&self, row: u32, col: u32, side: Cell) -> ConnectNGameState {
+        let mut new_board = self.board.clone();
+        new_board.set_cell(row, col, side);
+
+        ConnectNGameState {
+            board: new_board,
+            last_move: BoardMove {
+                coords: Coords { row, col },
+                side,
+            },
+            num_winner: self.num_winner,
+            gravity: self.gravity,
+        }
+    }
+}
+
+/// Check that `num_winner` in a row fits on a board of `height` x `width`.
+fn validate_rules(height: u32, width: u32, num_winner: i32) -> Result<(), Error> {
+    if height == 0 || width == 0 {
+        return Err(Error::InvalidPosition(format!(
+            "A board of {}x{} cells has no cells",
+            height, width
+        )));
+    }
+    if num_winner < 1 || num_winner as u32 > height.max(width) {
+        return Err(Error::InvalidPosition(format!(
+            "{} in a row cannot be won on a board of {}x{} cells",
+            num_winner, height, width
+        )));
+    }
+
+    Ok(())
+}
+
+impl GameState for ConnectNGameState {
+    fn expand(&self) -> Vec<ConnectNGameState> {
+        let next_side = match self.last_move.side {
+            Cell::X => Cell::O,
+            Cell::O => Cell::X,
+            Cell::Empty => panic!("Last move cannot be empty!"),
+        };
+
+        if self.gravity {
+            // The lowest empty cell of each column is the only move in it
+            (0..self.board.width())
+                .filter_map(|col| {
+                    (0..self.board.height())
+                        .rev()
+                        .find(|&row| self.board.get_cell(row, col).ok() == Some(Cell::Empty))
+                        .map(|row| self.child_state(row, col, next_side))
+                })
+                .collect()
+        } else {
+            self.board
+                .cells()
+                .iter()
+                .enumerate()
+                .filter(|(_, &cell)| cell == Cell::Empty)
+                .map(|(idx, _)| {
+                    let Coords { row, col } = self.board.get_coords(idx);
+                    self.child_state(row, col, next_side)
+                })
+                .collect()
+        }
+    }
+
+    fn position_value(&self) -> i32 {
+        match self
+            .board
+            .line_winner(&self.last_move.coords, self.num_winner)
+        {
+            Cell::X => X_WIN_VALUE,
+            Cell::O => -X_WIN_VALUE,
+            Cell::Empty => 0,
+        }
+    }
+
+    fn heuristic_value(&self, heuristic: Heuristic) -> i32 {
+        match self.outcome() {
+            GameOutcome::Ongoing => heuristic.evaluate(&self.board, self.num_winner),
+            _ => self.position_value(),
+        }
+    }
+
+    fn side(&self) -> Cell {
+        self.last_move.side
+    }
+
+    fn validate_coords(&self, coords: &Coords) -> Result<(), IllegalMoveReason> {
+        self.board.validate_placement(coords, self.gravity)
+    }
+
+    fn board_size(&self) -> (u32, u32) {
+        (self.board.height(), self.board.width())
+    }
+
+    fn last_move(&self) -> BoardMove {
+        self.last_move
+    }
+
+    fn outcome(&self) -> GameOutcome {
+        self.board.outcome(&self.last_move.coords, self.num_winner)
+    }
+
+    fn zobrist_hash(&self) -> u64 {
+        match self.last_move.side {
+            Cell::X => self.board.zobrist_hash() ^ SIDE_KEY,
+            _ => self.board.zobrist_hash(),
+        }
+    }
+
+    fn symmetries(&self) -> Vec<Symmetry> {
+        self.board.symmetries(self.gravity)
+    }
+
+    fn transformed(&self, symmetry: Symmetry) -> Self {
+        Self {
+            board: self.board.transformed(symmetry),
+            last_move: symmetry.map_move(&self.last_move, self.board.height(), self.board.width()),
+            num_winner: self.num_winner,
+            gravity: self.gravity,
+        }
+    }
+
+    fn canonical_hash(&self) -> u64 {
+        let (board, _) = self.board.canonical(&self.symmetries());
+        match self.last_move.side {
+            Cell::X => board.zobrist_hash() ^ SIDE_KEY,
+            _ => board.zobrist_hash(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        AlphaBetaSearch, Board, BoardMove, Cell, ConnectNGameInterface, ConnectNGameState, Error,
+        FiarGameState, GameOutcome, GameState, Heuristic, T3GameState, X_WIN_VALUE,
+    };
+
+    #[test]
+    fn test_connect_n_expand() {
+        // Gravity only allows the lowest empty cell per column
+        let connect_four = ConnectNGameState::new_game(6, 7, 4, true).unwrap();
+        let children = connect_four.expand();
+        assert_eq!(children.len(), 7);
+        assert!(children
+            .iter()
+            .all(|state| state.last_move().coords.row == 5));
+
+        let fiar_moves: Vec<BoardMove> = FiarGameState::default()
+            .expand()
+            .iter()
+            .flat_map(|state| state.expand())
+            .map(|state| state.last_move())
+            .collect();
+        let connect_four_moves: Vec<BoardMove> = children
+            .iter()
+            .flat_map(|state| state.expand())
+            .map(|state| state.last_move())
+            .collect();
+        assert_eq!(connect_four_moves, fiar_moves);
+
+        // Free placement allows every empty cell
+        let gomoku = ConnectNGameState::default();
+        assert_eq!(gomoku.expand().len(), 225);
+
+        let t3_moves: Vec<BoardMove> = T3GameState::default()
+            .expand()
+            .iter()
+            .map(|state| state.last_move())
+            .collect();
+        let connect_three_moves: Vec<BoardMove> = ConnectNGameState::new_game(3, 3, 3, false)
+            .unwrap()
+            .expand()
+            .iter()
+            .map(|state| state.last_move())
+            .collect();
+        assert_eq!(connect_three_moves, t3_moves);
+    }
+
+    #[test]
+    fn test_connect_n_five_in_a_row() {
+        let mut board = Board::new(15, 15);
+        for col in 3..7 {
+            board.set_cell(7, col, Cell::X);
+            board.set_cell(8, col, Cell::O);
+        }
+
+        // Four in a row do not win gomoku
+        let four = ConnectNGameState::new(board.clone(), BoardMove::new(7, 6, Cell::X), 5, false);
+        assert_eq!(four.outcome(), GameOutcome::Ongoing);
+        assert_eq!(four.position_value(), 0);
+
+        // The fifth piece completes the row
+        board.set_cell(7, 7, Cell::X);
+        let five = ConnectNGameState::new(board, BoardMove::new(7, 7, Cell::X), 5, false);
+        assert_eq!(five.outcome(), GameOutcome::Win(Cell::X));
+        assert_eq!(five.position_value(), X_WIN_VALUE);
+    }
+
+    #[test]
+    fn test_connect_n_search() {
+        // Three in a row on a 4x4 board with gravity
+        // . . . .
+        // . . . .
+        // . . . O
+        // X X . O
+        // -> X completes the row in the third column
+        let mut board = Board::new(4, 4);
+        board.set_cell(3, 0, Cell::X);
+        board.set_cell(3, 1, Cell::X);
+        board.set_cell(3, 3, Cell::O);
+        board.set_cell(2, 3, Cell::O);
+        let state = ConnectNGameState::new(board, BoardMove::new(2, 3, Cell::O), 3, true);
+
+        let children = state.expand();
+        let child_refs: Vec<&ConnectNGameState> = children.iter().collect();
+        let mut search = AlphaBetaSearch::new(Heuristic::Lines);
+        let (best_pos, value) = search.best_child(&state, &child_refs, 3).unwrap();
+
+        assert_eq!(
+            children[best_pos].last_move(),
+            BoardMove::new(3, 2, Cell::X)
+        );
+        assert_eq!(value, X_WIN_VALUE);
+    }
+
+    #[test]
+    fn test_connect_n_rules() {
+        for (height, width, num_winner) in [(0, 0, 3), (0, 5, 3), (4, 4, 0), (4, 4, -1), (2, 2, 5)]
+        {
+            assert!(matches!(
+                ConnectNGameInterface::new(height, width, num_winner, false),
+                Err(Error::InvalidPosition(_))
+            ));
+        }
+        // The line only has to fit in one direction
+        assert!(ConnectNGameState::new_game(1, 5, 5, false).is_ok());
+        assert!(ConnectNGameState::from_notation("3/3/3 X", 4, false).is_err());
+    }
+}
@@ -0,0 +1,292 @@
+use crate::{BoardMove, Rng};
+use wasm_bindgen::prelude::*;
+
+/// Presets for the strength of the AI.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DifficultyLevel {
+    /// Looks two plies ahead and often plays a random worse move
+    Beginner,
+    Easy,
+    Medium,
+    /// Plays the best move the search finds
+    Perfect,
+}
+
+/// Choice between moves with the same value.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TieBreak {
+    /// The first of the generated moves, e.g. the leftmost column
+    First,
+    /// A random move drawn with the seeded generator
+    Random,
+    /// The move closest to the center column, then to the center row
+    Center,
+}
+
+impl TieBreak {
+    /// Position of the move to play out of the equally valued `candidates`,
+    /// which are positions in `moves`.
+    pub fn choose(
+        &self,
+        candidates: &[usize],
+        moves: &[BoardMove],
+        (height, width): (u32, u32),
+        rng: &mut Rng,
+    ) -> Option<usize> {
+        match self {
+            TieBreak::First => candidates.first().copied(),
+            TieBreak::Random if candidates.is_empty() => None,
+            TieBreak::Random => Some(candidates[rng.gen_index(candidates.len())]),
+            // Distances are doubled to stay integers on even-sized boards
+            TieBreak::Center => candidates.iter().copied().min_by_key(|&pos| {
+                let coords = moves[pos].coords;
+                (
+                    (2 * coords.col).abs_diff(width - 1),
+                    (2 * coords.row).abs_diff(height - 1),
+                )
+            }),
+        }
+    }
+}
+
+/// Positions of the best `scores`, which are maximal if `maximizing`.
+pub fn best_positions(scores: &[i32], maximizing: bool) -> Vec<usize> {
+    let best = match maximizing {
+        true => scores.iter().max(),
+        false => scores.iter().min(),
+    };
+
+    (0..scores.len())
+        .filter(|&pos| Some(&scores[pos]) == best)
+        .collect()
+}
+
+/// Deliberate imperfections of the AI when choosing a move.
+///
+/// The defaults play perfectly. Scores are the values of the children of a
+/// position, so the temperature is in units of the heuristic (e.g. 10 for an
+/// open three of Four-in-a-row), while won and lost positions are far apart
+/// and never confused.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Difficulty {
+    /// Maximum number of plies the AI looks ahead
+    pub depth_limit: Option<usize>,
+    /// Probability of playing a random move which is worse than the best one
+    pub blunder_probability: f64,
+    /// Temperature of the softmax over the scores, 0 always picks the best
+    pub temperature: f64,
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        Self::from_level(DifficultyLevel::Perfect)
+    }
+}
+
+impl Difficulty {
+    pub fn from_level(level: DifficultyLevel) -> Self {
+        let (depth_limit, blunder_probability, temperature) = match level {
+            DifficultyLevel::Beginner => (Some(2), 0.4, 50.0),
+            DifficultyLevel::Easy => (Some(4), 0.2, 20.0),
+            DifficultyLevel::Medium => (Some(6), 0.05, 5.0),
+            DifficultyLevel::Perfect => (None, 0.0, 0.0),
+        };
+
+        Self {
+            depth_limit,
+            blunder_probability,
+            temperature,
+        }
+    }
+
+    /// Whether the best move is always played.
+    pub fn picks_best(&self) -> bool {
+        self.blunder_probability <= 0.0 && self.temperature <= 0.0
+    }
+
+    /// Restrict a search `depth` to the depth limit.
+    pub fn limit_depth(&self, depth: usize) -> usize {
+        match self.depth_limit {
+            Some(limit) => depth.min(limit.max(1)),
+            None => depth,
+        }
+    }
+
+    /// Position of the move to play out of the children with `scores`.
+    ///
+    /// With `maximizing`, high scores are good for the side to move. First,
+    /// a worse move is played with the blunder probability. Otherwise, moves
+    /// are sampled with the softmax of their scores. Without temperature,
+    /// `None` leaves the choice between the best moves to a `TieBreak`.
+    pub fn pick(&self, scores: &[i32], maximizing: bool, rng: &mut Rng) -> Option<usize> {
+        let values: Vec<f64> = scores
+            .iter()
+            .map(|&score| match maximizing {
+                true => score as f64,
+                false => -(score as f64),
+            })
+            .collect();
+        let best = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+        if self.blunder_probability > 0.0 && rng.next_f64() < self.blunder_probability {
+            let worse: Vec<usize> = (0..values.len())
+                .filter(|&pos| values[pos] < best)
+                .collect();
+            if !worse.is_empty() {
+                return Some(worse[rng.gen_index(worse.len())]);
+            }
+        }
+
+        if self.temperature <= 0.0 {
+            return None;
+        }
+
+        // Relative to the best value, so that the weights do not overflow
+        let weights: Vec<f64> = values
+            .iter()
+            .map(|value| ((value - best) / self.temperature).exp())
+            .collect();
+        let mut pick = rng.next_f64() * weights.iter().sum::<f64>();
+        for (pos, weight) in weights.iter().enumerate() {
+            if pick < *weight {
+                return Some(pos);
+            }
+            pick -= weight;
+        }
+
+        // Rounding errors of the sum
+        values.iter().position(|&value| value == best)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{best_positions, Difficulty, DifficultyLevel, TieBreak};
+    use crate::{
+        BoardMove, Cell, Coords, ExpandResult, FiarGameInterface, Rng, T3GameInterface, X_WIN_VALUE,
+    };
+
+    #[test]
+    fn test_pick() {
+        let scores = [10, 30, -X_WIN_VALUE, 20];
+        let mut rng = Rng::new(19);
+
+        let perfect = Difficulty::default();
+        assert!(perfect.picks_best());
+        assert_eq!(perfect.pick(&scores, true, &mut rng), None);
+        assert_eq!(best_positions(&scores, true), vec![1]);
+        assert_eq!(best_positions(&scores, false), vec![2]);
+        assert_eq!(perfect.limit_depth(9), 9);
+
+        // The softmax prefers better moves but never plays a lost one
+        let soft = Difficulty {
+            temperature: 10.0,
+            ..Default::default()
+        };
+        let mut counts = [0; 4];
+        for _ in 0..1000 {
+            counts[soft.pick(&scores, true, &mut rng).unwrap()] += 1;
+        }
+        assert!(counts[1] > counts[3] && counts[3] > counts[0]);
+        assert_eq!(counts[2], 0);
+
+        // Blunders are always worse than the best move
+        let blundering = Difficulty {
+            blunder_probability: 1.0,
+            ..Default::default()
+        };
+        for _ in 0..20 {
+            assert_ne!(blundering.pick(&scores, true, &mut rng), Some(1));
+        }
+        assert_eq!(blundering.pick(&[5, 5], true, &mut rng), None);
+
+        // The same seed yields the same moves
+        let beginner = Difficulty::from_level(DifficultyLevel::Beginner);
+        assert_eq!(beginner.limit_depth(9), 2);
+        let picks = |seed| {
+            let mut rng = Rng::new(seed);
+            (0..20)
+                .map(|_| beginner.pick(&scores, true, &mut rng).unwrap())
+                .collect::<Vec<usize>>()
+        };
+        assert_eq!(picks(7), picks(7));
+    }
+
+    #[test]
+    fn test_interface_difficulty() {
+        let play_game = |seed| {
+            let mut game_if = T3GameInterface::new();
+            game_if.set_difficulty(DifficultyLevel::Beginner);
+            game_if.set_seed(seed);
+            while !game_if.outcome().is_over() {
+                game_if.get_best_move().unwrap();
+            }
+            game_if.record_text()
+        };
+        assert_eq!(play_game(3), play_game(3));
+
+        // X can win immediately, but always blunders
+        let mut game_if = T3GameInterface::from_notation("XX1/OO1/3 X").unwrap();
+        game_if.set_blunder_probability(1.0);
+        assert_ne!(
+            game_if.get_best_move().unwrap().coords,
+            Coords { row: 0, col: 2 }
+        );
+
+        // Limiting the depth alone still plays the best move within it
+        let mut game_if = T3GameInterface::from_notation("XX1/OO1/3 X").unwrap();
+        game_if.expand_one_level();
+        game_if.set_depth_limit(Some(1));
+        assert_eq!(
+            game_if.get_best_move().unwrap().coords,
+            Coords { row: 0, col: 2 }
+        );
+
+        // The depth limit also applies to budgets and skips the book
+        let mut game_if = FiarGameInterface::new();
+        game_if.set_depth_limit(Some(3));
+        let result = game_if.get_best_move_with_nodes(1_000_000).unwrap();
+        assert_eq!(result.depth, 3);
+    }
+
+    #[test]
+    fn test_tie_break() {
+        let moves: Vec<BoardMove> = (0..7)
+            .map(|col| BoardMove {
+                coords: Coords { row: 5, col },
+                side: Cell::X,
+            })
+            .collect();
+        let candidates = [1, 2, 4, 6];
+        let mut rng = Rng::new(20);
+
+        let mut choose = |tie_break: TieBreak| {
+            tie_break
+                .choose(&candidates, &moves, (6, 7), &mut rng)
+                .unwrap()
+        };
+        assert_eq!(choose(TieBreak::First), 1);
+        assert_eq!(choose(TieBreak::Center), 2);
+        let mut chosen: Vec<usize> = (0..50).map(|_| choose(TieBreak::Random)).collect();
+        chosen.sort();
+        chosen.dedup();
+        assert_eq!(chosen, candidates);
+
+        // All first moves of tic-tac-toe lead to a draw
+        let first_move = |tie_break, seed| {
+            let mut game_if = T3GameInterface::new();
+            while game_if.expand_one_level() == ExpandResult::NotDone {}
+            game_if.set_tie_break(tie_break);
+            game_if.set_seed(seed);
+            game_if.get_best_move().unwrap().coords
+        };
+        assert_eq!(first_move(TieBreak::First, 0), Coords { row: 0, col: 0 });
+        assert_eq!(first_move(TieBreak::Center, 0), Coords { row: 1, col: 1 });
+        assert_eq!(
+            first_move(TieBreak::Random, 5),
+            first_move(TieBreak::Random, 5)
+        );
+    }
+}
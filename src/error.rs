@@ -0,0 +1,169 @@
+use crate::{BoardMove, Coords, GameOutcome};
+use serde::Serialize;
+use std::fmt;
+use wasm_bindgen::prelude::*;
+
+/// Errors of the engine.
+///
+/// In JS, they are thrown as `{ kind, message }` objects, e.g.
+/// `{ kind: "GameOver", message: "The game is already over: Draw" }`, so the
+/// UI can react to the kind and show the message. Illegal moves also carry
+/// the `reason`, e.g. `"ColumnFull"`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// A board or position which cannot be parsed or is impossible
+    InvalidPosition(String),
+    /// Coordinates outside of the board
+    OutOfBounds(Coords),
+    /// A game record which cannot be parsed or replayed
+    InvalidRecord(String),
+    /// An opening book which cannot be parsed
+    InvalidBook(String),
+    /// A move which cannot be played in the current position
+    IllegalMove(BoardMove, IllegalMoveReason),
+    /// A move was requested after the game ended
+    GameOver(GameOutcome),
+    /// A value from JS which does not have the expected shape
+    InvalidJsValue(String),
+}
+
+impl Error {
+    /// Name of the variant, used as `kind` in JS.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::InvalidPosition(_) => "InvalidPosition",
+            Error::OutOfBounds(_) => "OutOfBounds",
+            Error::InvalidRecord(_) => "InvalidRecord",
+            Error::InvalidBook(_) => "InvalidBook",
+            Error::IllegalMove(..) => "IllegalMove",
+            Error::GameOver(_) => "GameOver",
+            Error::InvalidJsValue(_) => "InvalidJsValue",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidPosition(message)
+            | Error::InvalidRecord(message)
+            | Error::InvalidBook(message)
+            | Error::InvalidJsValue(message) => write!(f, "{}", message),
+            Error::OutOfBounds(coords) => write!(
+                f,
+                "Row {} and column {} are out of bounds",
+                coords.row, coords.col
+            ),
+            Error::IllegalMove(board_move, reason) => write!(
+                f,
+                "Move of {:?} to row {} and column {} is not legal: {}",
+                board_move.side, board_move.coords.row, board_move.coords.col, reason
+            ),
+            Error::GameOver(outcome) => write!(f, "The game is already over: {:?}", outcome),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Rule which forbids a move.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IllegalMoveReason {
+    /// The game already ended
+    GameOver,
+    /// The move is by the side which moved last
+    WrongSide,
+    /// The coordinates are outside of the board
+    OutOfBounds,
+    /// The cell already holds a piece
+    CellOccupied,
+    /// With gravity, the column has no empty cell left
+    ColumnFull,
+    /// With gravity, the cell below is still empty
+    Floating,
+}
+
+impl fmt::Display for IllegalMoveReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            IllegalMoveReason::GameOver => "the game is over",
+            IllegalMoveReason::WrongSide => "the other side is to move",
+            IllegalMoveReason::OutOfBounds => "the cell is out of bounds",
+            IllegalMoveReason::CellOccupied => "the cell is occupied",
+            IllegalMoveReason::ColumnFull => "the column is full",
+            IllegalMoveReason::Floating => "the cell below is empty",
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+impl From<serde_wasm_bindgen::Error> for Error {
+    fn from(err: serde_wasm_bindgen::Error) -> Self {
+        Error::InvalidJsValue(err.to_string())
+    }
+}
+
+#[derive(Serialize)]
+struct JsError {
+    kind: &'static str,
+    message: String,
+    /// Variant of `IllegalMoveReason` for illegal moves
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+}
+
+impl From<Error> for JsValue {
+    fn from(err: Error) -> Self {
+        let js_error = JsError {
+            kind: err.kind(),
+            message: err.to_string(),
+            reason: match &err {
+                Error::IllegalMove(_, reason) => Some(format!("{:?}", reason)),
+                _ => None,
+            },
+        };
+        serde_wasm_bindgen::to_value(&js_error)
+            .unwrap_or_else(|_| JsValue::from_str(&js_error.message))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Error, IllegalMoveReason};
+    use crate::{BoardMove, Cell, GameOutcome, T3GameInterface};
+
+    #[test]
+    fn test_error_messages() {
+        let err = Error::IllegalMove(BoardMove::new(1, 2, Cell::X), IllegalMoveReason::ColumnFull);
+        assert_eq!(err.kind(), "IllegalMove");
+        assert_eq!(
+            err.to_string(),
+            "Move of X to row 1 and column 2 is not legal: the column is full"
+        );
+        assert_eq!(
+            Error::GameOver(GameOutcome::Draw).to_string(),
+            "The game is already over: Draw"
+        );
+    }
+
+    #[test]
+    fn test_interface_errors() {
+        // X has won, so O cannot move anymore
+        let mut game_if = T3GameInterface::from_notation("XXX/OO1/3 O").unwrap();
+        assert_eq!(
+            game_if.get_best_move(),
+            Err(Error::GameOver(GameOutcome::Win(Cell::X)))
+        );
+        assert_eq!(
+            game_if.track_move(BoardMove::new(1, 2, Cell::O)),
+            Err(Error::IllegalMove(
+                BoardMove::new(1, 2, Cell::O),
+                IllegalMoveReason::GameOver
+            ))
+        );
+        assert!(matches!(
+            T3GameInterface::from_notation("XXXX/3/3 O"),
+            Err(Error::InvalidPosition(_))
+        ));
+    }
+}
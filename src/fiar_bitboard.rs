@@ -0,0 +1,483 @@
+use crate::heuristic::{window_score, CENTER_SCORE};
+use crate::transposition::{zobrist_key, SIDE_KEY};
+use crate::{
+    Board, BoardMove, Cell, Coords, Error, GameOutcome, GameState, Heuristic, IllegalMoveReason,
+    Symmetry, HEURISTIC_LIMIT, X_WIN_VALUE,
+};
+
+const HEIGHT: u32 = 6;
+const WIDTH: u32 = 7;
+/// Bits per column, the extra bit on top keeps lines from wrapping around
+const COLUMN_BITS: u32 = HEIGHT + 1;
+/// Shifts to the neighbor cell vertically, diagonally down, horizontally and
+/// diagonally up.
+const DIRECTIONS: [u32; 4] = [1, COLUMN_BITS - 1, COLUMN_BITS, COLUMN_BITS + 1];
+/// Mask of all cells on the board
+const FULL_MASK: u64 = full_mask();
+/// Zobrist keys of X and O for each board index, computed at compile time
+const ZOBRIST_KEYS: [[u64; (HEIGHT * WIDTH) as usize]; 2] = zobrist_keys();
+/// Cells where a window of four cells in the direction of `DIRECTIONS` at
+/// the same index starts without leaving the board
+const WINDOW_STARTS: [u64; 4] = window_starts();
+/// Mask `k` holds the cells which are more than `k` steps away from the
+/// edges, so each piece is counted once per step towards the center.
+const CENTER_MASKS: [u64; 5] = center_masks();
+
+/// Four-in-a-row state with one bit mask per side.
+///
+/// Bit `col * 7 + h` is the cell in column `col` at height `h` counted from
+/// the bottom. The column heights are the trailing ones of both masks
+/// combined. Children only set a bit instead of cloning a board, and four in
+/// a row are found by and-ing the mask with shifted copies of itself.
+/// The Zobrist hash is the same as for `FiarGameState`, so both can be
+/// compared and share transposition tables.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FiarBitboardState {
+    /// Pieces of X and O
+    masks: [u64; 2],
+    last_move: BoardMove,
+    hash: u64,
+}
+
+impl FiarBitboardState {
+    /// Convert a `board` of 6x7 cells where `last_move` was played last.
+    pub fn from_board(board: &Board, last_move: BoardMove) -> Self {
+        let mut state = FiarBitboardState::default();
+        for col in 0..WIDTH {
+            for row in (0..HEIGHT).rev() {
+                match board.get_cell(row, col) {
+                    Ok(Cell::Empty) | Err(_) => break,
+                    Ok(side) => state = state.with_piece(col, side),
+                }
+            }
+        }
+
+        FiarBitboardState { last_move, ..state }
+    }
+
+    /// Load a position like `FiarGameState::from_notation`.
+    pub fn from_notation(notation: &str) -> Result<Self, Error> {
+        let (board, side_to_move) = Board::from_notation(notation)?;
+        board.validate_size(HEIGHT, WIDTH, "Four-in-a-row")?;
+
+        let last_move = board.infer_last_move(side_to_move, 4, true)?;
+        board.validate_winner(&last_move, 4)?;
+        Ok(Self::from_board(&board, last_move))
+    }
+
+    /// Board with the same cells, e.g. to evaluate it with a `Heuristic`.
+    pub fn to_board(&self) -> Board {
+        let mut board = Board::new(HEIGHT, WIDTH);
+        for col in 0..WIDTH {
+            for height in 0..self.height(col) {
+                let side = match self.masks[0] & bit(col, height) {
+                    0 => Cell::O,
+                    _ => Cell::X,
+                };
+                board.set_cell(HEIGHT - 1 - height, col, side);
+            }
+        }
+
+        board
+    }
+
+    /// Board and side to move in the notation of `Board::from_notation`.
+    pub fn to_notation(&self) -> String {
+        let side_to_move = match self.last_move.side {
+            Cell::X => Cell::O,
+            _ => Cell::X,
+        };
+        self.to_board().to_notation(side_to_move)
+    }
+
+    /// Number of pieces in `col`, which stack up without gaps from bit 0.
+    #[inline]
+    fn height(&self, col: u32) -> u32 {
+        ((self.masks[0] | self.masks[1]) >> (col * COLUMN_BITS)).trailing_ones()
+    }
+
+    /// Copy with a piece of `side` dropped into `col`, which must not be full.
+    ///
+    /// The copy is built from scratch instead of changing a clone, so that
+    /// children stay in registers until they are stored.
+    #[inline]
+    fn with_piece(&self, col: u32, side: Cell) -> Self {
+        let height = self.height(col);
+        let row = HEIGHT - 1 - height;
+        let mut masks = self.masks;
+        masks[side_idx(side)] |= bit(col, height);
+
+        FiarBitboardState {
+            masks,
+            last_move: BoardMove {
+                coords: Coords { row, col },
+                side,
+            },
+            hash: self.hash ^ ZOBRIST_KEYS[side_idx(side)][(row * WIDTH + col) as usize],
+        }
+    }
+
+    /// Same value as `Heuristic::Lines` on `to_board()`, counted on the masks.
+    fn lines_value(&self) -> i32 {
+        let [x_mask, o_mask] = self.masks;
+        let mut value = windows_value(x_mask, o_mask) - windows_value(o_mask, x_mask);
+
+        for mask in CENTER_MASKS {
+            let closer = (x_mask & mask).count_ones() as i32 - (o_mask & mask).count_ones() as i32;
+            value += CENTER_SCORE * closer;
+        }
+
+        value.clamp(-HEURISTIC_LIMIT, HEURISTIC_LIMIT)
+    }
+
+    #[inline]
+    fn next_side(&self) -> Cell {
+        match self.last_move.side {
+            Cell::X => Cell::O,
+            Cell::O => Cell::X,
+            Cell::Empty => panic!("Last move cannot be empty!"),
+        }
+    }
+
+    #[inline]
+    fn winner(&self) -> Cell {
+        // Only the side of the last move can have completed a line
+        let side = self.last_move.side;
+        if side == Cell::Empty || !has_four(self.masks[side_idx(side)]) {
+            return Cell::Empty;
+        }
+
+        side
+    }
+}
+
+impl Default for FiarBitboardState {
+    fn default() -> Self {
+        Self {
+            masks: [0, 0],
+            last_move: BoardMove {
+                coords: Coords { row: 0, col: 0 },
+                // We usually start with X, so the "last" was O
+                side: Cell::O,
+            },
+            hash: 0,
+        }
+    }
+}
+
+impl GameState for FiarBitboardState {
+    fn expand(&self) -> Vec<FiarBitboardState> {
+        // We cannot have more children states as columns
+        let next_side = self.next_side();
+        let mut next_states = Vec::with_capacity(WIDTH as usize);
+        for col in 0..WIDTH {
+            if self.height(col) < HEIGHT {
+                next_states.push(self.with_piece(col, next_side));
+            }
+        }
+
+        next_states
+    }
+
+    #[inline]
+    fn children(&self) -> impl Iterator<Item = FiarBitboardState> {
+        let next_side = self.next_side();
+        let state = *self;
+        (0..WIDTH).filter_map(move |col| {
+            // Pieces cannot be dropped into full columns
+            if state.height(col) == HEIGHT {
+                return None;
+            }
+
+            Some(state.with_piece(col, next_side))
+        })
+    }
+
+    fn position_value(&self) -> i32 {
+        match self.winner() {
+            Cell::X => X_WIN_VALUE,
+            Cell::O => -X_WIN_VALUE,
+            Cell::Empty => 0,
+        }
+    }
+
+    fn heuristic_value(&self, heuristic: Heuristic) -> i32 {
+        match (self.outcome(), heuristic) {
+            (GameOutcome::Ongoing, Heuristic::WinLoss) => 0,
+            (GameOutcome::Ongoing, Heuristic::Lines) => self.lines_value(),
+            _ => self.position_value(),
+        }
+    }
+
+    fn side(&self) -> Cell {
+        self.last_move.side
+    }
+
+    fn validate_coords(&self, coords: &Coords) -> Result<(), IllegalMoveReason> {
+        if coords.row >= HEIGHT || coords.col >= WIDTH {
+            return Err(IllegalMoveReason::OutOfBounds);
+        }
+
+        // Pieces land in the row above the filled cells of the column
+        let height = self.height(coords.col);
+        match HEIGHT.checked_sub(height + 1) {
+            None => Err(IllegalMoveReason::ColumnFull),
+            Some(row) if coords.row > row => Err(IllegalMoveReason::CellOccupied),
+            Some(row) if coords.row < row => Err(IllegalMoveReason::Floating),
+            Some(_) => Ok(()),
+        }
+    }
+
+    fn board_size(&self) -> (u32, u32) {
+        (HEIGHT, WIDTH)
+    }
+
+    fn last_move(&self) -> BoardMove {
+        self.last_move
+    }
+
+    #[inline]
+    fn outcome(&self) -> GameOutcome {
+        match self.winner() {
+            Cell::Empty if self.masks[0] | self.masks[1] == FULL_MASK => GameOutcome::Draw,
+            Cell::Empty => GameOutcome::Ongoing,
+            side => GameOutcome::Win(side),
+        }
+    }
+
+    fn zobrist_hash(&self) -> u64 {
+        match self.last_move.side {
+            Cell::X => self.hash ^ SIDE_KEY,
+            _ => self.hash,
+        }
+    }
+
+    fn symmetries(&self) -> Vec<Symmetry> {
+        vec![Symmetry::Identity, Symmetry::MirrorColumns]
+    }
+
+    fn transformed(&self, symmetry: Symmetry) -> Self {
+        Self::from_board(
+            &self.to_board().transformed(symmetry),
+            symmetry.map_move(&self.last_move, HEIGHT, WIDTH),
+        )
+    }
+
+    fn canonical_hash(&self) -> u64 {
+        let (board, _) = self.to_board().canonical(&self.symmetries());
+        match self.last_move.side {
+            Cell::X => board.zobrist_hash() ^ SIDE_KEY,
+            _ => board.zobrist_hash(),
+        }
+    }
+}
+
+#[inline]
+const fn bit(col: u32, height: u32) -> u64 {
+    1 << (col * COLUMN_BITS + height)
+}
+
+#[inline]
+fn side_idx(side: Cell) -> usize {
+    match side {
+        Cell::O => 1,
+        _ => 0,
+    }
+}
+
+const fn full_mask() -> u64 {
+    let column = (1 << HEIGHT) - 1;
+    let mut mask = 0;
+    let mut col = 0;
+    while col < WIDTH {
+        mask |= column << (col * COLUMN_BITS);
+        col += 1;
+    }
+    mask
+}
+
+const fn zobrist_keys() -> [[u64; (HEIGHT * WIDTH) as usize]; 2] {
+    let mut keys = [[0; (HEIGHT * WIDTH) as usize]; 2];
+    let mut idx = 0;
+    while idx < keys[0].len() {
+        keys[0][idx] = zobrist_key(idx, 0);
+        keys[1][idx] = zobrist_key(idx, 1);
+        idx += 1;
+    }
+    keys
+}
+
+const fn window_starts() -> [u64; 4] {
+    // Steps in columns and heights along the lines of `DIRECTIONS`
+    let steps: [(i32, i32); 4] = [(0, 1), (1, -1), (1, 0), (1, 1)];
+    let mut starts = [0; 4];
+    let mut dir = 0;
+    while dir < steps.len() {
+        let (d_col, d_height) = steps[dir];
+        let mut col = 0;
+        while col < WIDTH as i32 {
+            let mut height = 0;
+            while height < HEIGHT as i32 {
+                let (end_col, end_height) = (col + 3 * d_col, height + 3 * d_height);
+                if end_col < WIDTH as i32 && 0 <= end_height && end_height < HEIGHT as i32 {
+                    starts[dir] |= bit(col as u32, height as u32);
+                }
+                height += 1;
+            }
+            col += 1;
+        }
+        dir += 1;
+    }
+    starts
+}
+
+const fn center_masks() -> [u64; 5] {
+    let mut masks = [0; 5];
+    let mut col = 0;
+    while col < WIDTH {
+        let mut height = 0;
+        while height < HEIGHT {
+            // Doubled distances like in the center score of `Heuristic`
+            let row_dist = (2 * height).abs_diff(HEIGHT - 1);
+            let col_dist = (2 * col).abs_diff(WIDTH - 1);
+            let closeness = (HEIGHT - 1 - row_dist) / 2 + (WIDTH - 1 - col_dist) / 2;
+            let mut step = 0;
+            while step < closeness {
+                masks[step as usize] |= bit(col, height);
+                step += 1;
+            }
+            height += 1;
+        }
+        col += 1;
+    }
+    masks
+}
+
+/// Sum the scores of the windows which hold pieces of `mask` but not of
+/// `other`, like `Heuristic::Lines` does for one side.
+fn windows_value(mask: u64, other: u64) -> i32 {
+    let mut value = 0;
+    for (shift, starts) in DIRECTIONS.into_iter().zip(WINDOW_STARTS) {
+        // Bit `i` of `cells[step]` is the cell `step` steps from `i`
+        let cells = [0, 1, 2, 3].map(|step| mask >> (step * shift));
+        let blocked = [0, 1, 2, 3].map(|step| other >> (step * shift));
+        let open = starts & !(blocked[0] | blocked[1] | blocked[2] | blocked[3]);
+
+        // Add up the four cells of all windows at once. The sum is `ones`
+        // plus two for each of the carries, of which at most two are set.
+        let (ones_a, carry_a) = (cells[0] ^ cells[1], cells[0] & cells[1]);
+        let (ones_b, carry_b) = (cells[2] ^ cells[3], cells[2] & cells[3]);
+        let (ones, carry_c) = (ones_a ^ ones_b, ones_a & ones_b);
+        let one_carry = carry_a ^ carry_b ^ carry_c;
+        let no_carry = !(carry_a | carry_b | carry_c);
+
+        for (num_pieces, windows) in [
+            (1, ones & no_carry),
+            (2, one_carry & !ones),
+            (3, one_carry & ones),
+        ] {
+            value += window_score(4 - num_pieces) * (open & windows).count_ones() as i32;
+        }
+    }
+    value
+}
+
+/// Check if `mask` contains four bits in a row in any direction.
+#[inline]
+fn has_four(mask: u64) -> bool {
+    // Combining all directions without early return avoids branches
+    let mut found = 0;
+    for shift in DIRECTIONS {
+        let pairs = mask & (mask >> shift);
+        found |= pairs & (pairs >> (2 * shift));
+    }
+    found != 0
+}
+
+#[cfg(test)]
+mod test {
+    use super::{FiarBitboardState, HEIGHT, WIDTH};
+    use crate::{
+        perft, BoardMove, Cell, FiarGameState, GameOutcome, GameState, Heuristic, Rng, X_WIN_VALUE,
+    };
+
+    #[test]
+    fn test_bitboard_perft() {
+        let state = FiarBitboardState::default();
+        let counts: Vec<usize> = (1..=7).map(|depth| perft(&state, depth)).collect();
+        assert_eq!(counts, vec![7, 49, 343, 2401, 16807, 117649, 823536]);
+    }
+
+    #[test]
+    fn test_bitboard_matches_board() {
+        let mut rng = Rng::new(16);
+        let mut num_wins = 0;
+
+        for _ in 0..200 {
+            let mut board_state = FiarGameState::default();
+            let mut bit_state = FiarBitboardState::default();
+
+            loop {
+                assert_eq!(bit_state.to_notation(), board_state.to_notation());
+                assert_eq!(bit_state.last_move(), board_state.last_move());
+                assert_eq!(bit_state.outcome(), board_state.outcome());
+                assert_eq!(bit_state.zobrist_hash(), board_state.zobrist_hash());
+                assert_eq!(bit_state.canonical_hash(), board_state.canonical_hash());
+                for heuristic in [Heuristic::WinLoss, Heuristic::Lines] {
+                    assert_eq!(
+                        bit_state.heuristic_value(heuristic),
+                        board_state.heuristic_value(heuristic)
+                    );
+                }
+                // Including one row and column out of bounds
+                for row in 0..=HEIGHT {
+                    for col in 0..=WIDTH {
+                        let board_move = BoardMove::new(row, col, Cell::X);
+                        assert_eq!(
+                            bit_state.validate_move(&board_move),
+                            board_state.validate_move(&board_move)
+                        );
+                    }
+                }
+
+                if board_state.outcome().is_over() {
+                    if let GameOutcome::Win(_) = board_state.outcome() {
+                        num_wins += 1;
+                    }
+                    break;
+                }
+
+                let mut board_children = board_state.expand();
+                let mut bit_children = bit_state.expand();
+                assert_eq!(bit_children.len(), board_children.len());
+                assert_eq!(bit_state.children().collect::<Vec<_>>(), bit_children);
+
+                let pos = rng.gen_index(board_children.len());
+                board_state = board_children.swap_remove(pos);
+                bit_state = bit_children.swap_remove(pos);
+            }
+
+            // Converting from a board yields the same state
+            let converted =
+                FiarBitboardState::from_board(&bit_state.to_board(), bit_state.last_move());
+            assert_eq!(converted, bit_state);
+        }
+
+        // Random games should mostly end with a win
+        assert!(num_wins > 150);
+    }
+
+    #[test]
+    fn test_bitboard_wins() {
+        // Diagonal up from the bottom left
+        let state = FiarBitboardState::from_notation("7/7/3X3/2XO3/1XOX3/XOOOX2 O").unwrap();
+        assert_eq!(state.outcome(), GameOutcome::Win(Cell::X));
+        assert_eq!(state.position_value(), X_WIN_VALUE);
+
+        // The top of one column and the bottom of the next are not in a row
+        let state = FiarBitboardState::from_notation("X6/X6/O6/O6/XX5/OXOO3 X").unwrap();
+        assert_eq!(state.outcome(), GameOutcome::Ongoing);
+
+        assert!(FiarBitboardState::from_notation("3/3/3 X").is_err());
+    }
+}
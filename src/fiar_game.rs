@@ -1,8 +1,12 @@
-use crate::{Board, BoardMove, Cell, Coords, GameState, X_WIN_VALUE};
+use crate::transposition::SIDE_KEY;
+use crate::{
+    Board, BoardMove, Cell, Coords, Error, GameKind, GameOutcome, GameRecord, GameState, Heuristic,
+    IllegalMoveReason, Symmetry, WinningLines, X_WIN_VALUE,
+};
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct FiarGameState {
     board: Board,
     last_move: BoardMove,
@@ -10,10 +14,23 @@ pub struct FiarGameState {
 
 #[wasm_bindgen]
 impl FiarGameState {
+    /// State after `last_move` on `board`, which is not validated, see
+    /// `try_new`.
     pub fn new(board: Board, last_move: BoardMove) -> Self {
         Self { board, last_move }
     }
 
+    /// State after `last_move` on an imported `board`, e.g. filled with
+    /// `Board::set_state`.
+    ///
+    /// Fails if the board is not 6x7 or a win on the board was not
+    /// completed by `last_move`.
+    pub fn try_new(board: Board, last_move: BoardMove) -> Result<FiarGameState, Error> {
+        board.validate_size(6, 7, "Four-in-a-row")?;
+        board.validate_winner(&last_move, 4)?;
+        Ok(Self::new(board, last_move))
+    }
+
     pub fn side(&self) -> Cell {
         self.last_move.side
     }
@@ -21,6 +38,88 @@ impl FiarGameState {
     pub fn last_move(&self) -> BoardMove {
         self.last_move
     }
+
+    /// Outcome of the game as `{ kind, winner }` object.
+    #[wasm_bindgen(js_name = outcome)]
+    pub fn outcome_js(&self) -> Result<JsValue, JsValue> {
+        Ok(GameState::outcome(self).to_js_value()?)
+    }
+
+    /// Lines won by the last move as `{ winner, lines }` object, or `null`.
+    #[wasm_bindgen(js_name = winning_lines)]
+    pub fn winning_lines_js(&self) -> Result<JsValue, JsValue> {
+        Ok(WinningLines::option_to_js_value(&self.winning_lines())?)
+    }
+
+    /// Load a position like `7/7/7/7/7/3X3 O`, see `Board::from_notation`.
+    #[wasm_bindgen(js_name = from_notation)]
+    pub fn from_notation_js(notation: &str) -> Result<FiarGameState, JsValue> {
+        Self::from_notation(notation).map_err(JsValue::from)
+    }
+
+    /// Board and side to move in the notation of `Board::from_notation`.
+    pub fn to_notation(&self) -> String {
+        let side_to_move = match self.last_move.side {
+            Cell::X => Cell::O,
+            _ => Cell::X,
+        };
+        self.board.to_notation(side_to_move)
+    }
+
+    /// Move of the side to move into the lowest empty cell of `col`.
+    ///
+    /// Fails with the reason if the column is full, does not exist or the
+    /// game is over.
+    pub fn drop_in_column(&self, col: u32) -> Result<BoardMove, Error> {
+        let side = match self.last_move.side {
+            Cell::X => Cell::O,
+            _ => Cell::X,
+        };
+        let coords = self
+            .board
+            .first_empty_in_column(col)
+            .unwrap_or(Coords { row: 0, col });
+        let board_move = BoardMove { coords, side };
+
+        self.validate_move(&board_move)
+            .map_err(|reason| Error::IllegalMove(board_move, reason))?;
+        Ok(board_move)
+    }
+}
+
+impl FiarGameState {
+    /// Winner and every line completed by the last move.
+    pub fn winning_lines(&self) -> Option<WinningLines> {
+        self.board.winning_lines(&self.last_move.coords, 4)
+    }
+
+    pub fn from_notation(notation: &str) -> Result<Self, Error> {
+        let (board, side_to_move) = Board::from_notation(notation)?;
+        board.validate_size(6, 7, "Four-in-a-row")?;
+
+        let last_move = board.infer_last_move(side_to_move, 4, true)?;
+        Self::try_new(board, last_move)
+    }
+
+    /// Empty record of a game which starts at this state.
+    pub fn new_record(&self) -> GameRecord {
+        GameRecord::new(GameKind::FourInARow, 6, 7, 4, true, self.to_notation())
+    }
+
+    /// Start state of `record`, which has to be a game of Four-in-a-row.
+    pub fn from_record_start(record: &GameRecord) -> Result<Self, Error> {
+        if record.kind != GameKind::FourInARow {
+            return Err(Error::InvalidRecord(format!(
+                "Record of {:?} is not Four-in-a-row",
+                record.kind
+            )));
+        }
+
+        match &record.start {
+            Some(start) => Self::from_notation(start),
+            None => Ok(Self::default()),
+        }
+    }
 }
 
 impl Default for FiarGameState {
@@ -81,9 +180,58 @@ impl GameState for FiarGameState {
         }
     }
 
+    fn heuristic_value(&self, heuristic: Heuristic) -> i32 {
+        match self.outcome() {
+            GameOutcome::Ongoing => heuristic.evaluate(&self.board, 4),
+            _ => self.position_value(),
+        }
+    }
+
     fn side(&self) -> Cell {
         self.last_move.side
     }
+
+    fn validate_coords(&self, coords: &Coords) -> Result<(), IllegalMoveReason> {
+        self.board.validate_placement(coords, true)
+    }
+
+    fn board_size(&self) -> (u32, u32) {
+        (self.board.height(), self.board.width())
+    }
+
+    fn last_move(&self) -> BoardMove {
+        self.last_move
+    }
+
+    fn outcome(&self) -> GameOutcome {
+        self.board.outcome(&self.last_move.coords, 4)
+    }
+
+    fn zobrist_hash(&self) -> u64 {
+        match self.last_move.side {
+            Cell::X => self.board.zobrist_hash() ^ SIDE_KEY,
+            _ => self.board.zobrist_hash(),
+        }
+    }
+
+    fn symmetries(&self) -> Vec<Symmetry> {
+        vec![Symmetry::Identity, Symmetry::MirrorColumns]
+    }
+
+    fn transformed(&self, symmetry: Symmetry) -> Self {
+        Self {
+            board: self.board.transformed(symmetry),
+            last_move: symmetry.map_move(&self.last_move, 6, 7),
+        }
+    }
+
+    fn canonical_hash(&self) -> u64 {
+        let (board, _) = self.board.canonical(&self.symmetries());
+        match self.last_move.side {
+            Cell::X => board.zobrist_hash() ^ SIDE_KEY,
+            _ => board.zobrist_hash(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -124,4 +272,58 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_fiar_notation() {
+        let err = FiarGameState::from_notation("7/7/7/7/3O3/2XX3 X").unwrap_err();
+        assert!(err.to_string().contains("piece count"));
+
+        // X completed the bottom row with the last move
+        let state = FiarGameState::from_notation("7/7/7/7/1OOO3/1XXXX2 O").unwrap();
+        assert_eq!(state.last_move().side, Cell::X);
+        assert_eq!(state.outcome(), GameOutcome::Win(Cell::X));
+        assert_eq!(state.to_notation(), "7/7/7/7/1OOO3/1XXXX2 O");
+        let winning = state.winning_lines().unwrap();
+        assert_eq!(winning.winner, Cell::X);
+        assert_eq!(winning.lines.len(), 1);
+        assert!(winning.lines[0].iter().all(|coords| coords.row == 5));
+
+        let child = &FiarGameState::default().expand()[3];
+        let state = FiarGameState::from_notation(&child.to_notation()).unwrap();
+        assert_eq!(state.last_move(), child.last_move());
+        assert_eq!(state.zobrist_hash(), child.zobrist_hash());
+
+        assert!(FiarGameState::from_notation("3/3/3 X").is_err());
+        assert!(FiarGameState::try_new(Board::new(3, 3), child.last_move()).is_err());
+        assert!(FiarGameState::from_notation("7/7/7/7/3X3/7 O").is_err());
+        // The four of X are covered, so no last move of X can have won
+        let err = FiarGameState::from_notation("7/O6/X6/X6/X6/XOOO3 X").unwrap_err();
+        assert!(err.to_string().contains("4 in a row"));
+    }
+
+    #[test]
+    fn test_drop_in_column() {
+        let state = FiarGameState::from_notation("O6/X6/O6/X6/O6/XX5 O").unwrap();
+        assert_eq!(state.drop_in_column(1), Ok(BoardMove::new(4, 1, Cell::O)));
+
+        let reason = |col| match state.drop_in_column(col) {
+            Err(Error::IllegalMove(_, reason)) => reason,
+            result => panic!("Unexpected {:?}", result),
+        };
+        assert_eq!(reason(0), IllegalMoveReason::ColumnFull);
+        assert_eq!(reason(7), IllegalMoveReason::OutOfBounds);
+
+        let won = FiarGameState::from_notation("7/7/7/7/1OOO3/1XXXX2 O").unwrap();
+        assert!(matches!(
+            won.drop_in_column(0),
+            Err(Error::IllegalMove(_, IllegalMoveReason::GameOver))
+        ));
+
+        // The interfaces play the move and return where it landed
+        let mut game_if = FiarGameInterface::new();
+        assert_eq!(game_if.drop_in_column(3), Ok(BoardMove::new(5, 3, Cell::X)));
+        assert_eq!(game_if.drop_in_column(3), Ok(BoardMove::new(4, 3, Cell::O)));
+        let mut mcts_if = FiarMctsInterface::new();
+        assert_eq!(mcts_if.drop_in_column(6), Ok(BoardMove::new(5, 6, Cell::X)));
+    }
 }
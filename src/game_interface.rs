@@ -1,6 +1,12 @@
-use crate::{BoardMove, Cell, FiarGameState, T3GameState, TreeEvaluator, X_WIN_VALUE};
+use crate::logging::{debug, info, warn, LogEvent};
+use crate::utils::set_panic_hook;
+use crate::{
+    best_positions, now_ms, Analysis, BoardMove, Cell, ConnectNGameState, Difficulty,
+    DifficultyLevel, Error, FiarGameState, GameOutcome, GameRecord, GameState, Heuristic,
+    MctsEvaluator, OpeningBook, Rng, SearchBudget, T3GameState, TieBreak, TreeEvaluator,
+};
+use std::rc::Rc;
 use wasm_bindgen::prelude::*;
-use web_sys::console;
 
 #[wasm_bindgen]
 #[derive(PartialEq, Eq)]
@@ -9,134 +15,625 @@ pub enum ExpandResult {
     NotDone,
 }
 
+/// Search algorithm used to identify the best move.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchMode {
+    /// Evaluate the tree expanded level by level with minimax.
+    Minimax,
+    /// Search depth-first with alpha-beta pruning below the direct children.
+    AlphaBeta,
+}
+
+/// Upper limit for the depth of iterative deepening searches.
+///
+/// The deepening usually stops earlier when the time budget is used up or
+/// when the game is searched to its end.
+const MAX_SEARCH_DEPTH: usize = 64;
+
+/// Best move of a time- or node-limited search.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct SearchResult {
+    pub best_move: BoardMove,
+    /// Minimax value for tree searches, win rate in percent for MCTS
+    pub value: i32,
+    /// Depth of the deepest finished iteration or of the most visited line,
+    /// 0 for moves from the opening book
+    pub depth: usize,
+}
+
 macro_rules! gen_game_if_impl {
-    ( $game_if:ty, $max_depth:expr ) => {
+    (
+        $game_if:ty,
+        $game_state:ty,
+        $max_depth:expr,
+        $search_mode:expr,
+        $search_depth:expr,
+        $heuristic:expr,
+        $book:expr
+    ) => {
         #[wasm_bindgen]
         impl $game_if {
             pub fn new() -> Self {
-                console::log_1(&"Initialized a new GameInterface".into());
-                Self {
-                    tree_eval: TreeEvaluator::new_with_default(),
-                    last_move_idx: 0,
-                    expand_new_idx: vec![0],
-                    cur_expanded_depth: 0,
-                    max_expanded_depth: $max_depth,
+                Self::with_init_state(<$game_state>::default())
+            }
+
+            /// Start the game at a position in the notation of
+            /// `Board::from_notation`, e.g. to load a puzzle.
+            pub fn from_notation(notation: &str) -> Result<$game_if, Error> {
+                <$game_state>::from_notation(notation).map(Self::with_init_state)
+            }
+        }
+
+        gen_game_if_impl!(
+            @shared $game_if,
+            $game_state,
+            $max_depth,
+            $search_mode,
+            $search_depth,
+            $heuristic,
+            $book
+        );
+    };
+    // Everything but the constructor, which may take the rules of the game
+    (
+        @shared $game_if:ty,
+        $game_state:ty,
+        $max_depth:expr,
+        $search_mode:expr,
+        $search_depth:expr,
+        $heuristic:expr,
+        $book:expr
+    ) => {
+        #[wasm_bindgen]
+        impl $game_if {
+            /// Select the heuristic to estimate positions at the search horizon.
+            pub fn set_heuristic(&mut self, heuristic: Heuristic) {
+                debug(LogEvent::Message(format!("Set heuristic to {:?}", heuristic)));
+                self.heuristic = heuristic;
+                self.tree_eval.set_heuristic(heuristic);
+            }
+
+            /// Search only one of the moves which lead to symmetric positions.
+            ///
+            /// Playing one of the skipped moves maps the searched twin onto
+            /// the board, so the best moves are always in the orientation of
+            /// the game.
+            pub fn set_merge_symmetries(&mut self, merge_symmetries: bool) {
+                debug(LogEvent::Message(format!(
+                    "Set merging of symmetries to {}",
+                    merge_symmetries
+                )));
+                self.merge_symmetries = merge_symmetries;
+                self.tree_eval.set_merge_symmetries(merge_symmetries);
+            }
+
+            pub fn set_search_mode(&mut self, search_mode: SearchMode) {
+                debug(LogEvent::Message(format!(
+                    "Set search mode to {:?}",
+                    search_mode
+                )));
+                self.search_mode = search_mode;
+            }
+
+            pub fn set_search_depth(&mut self, search_depth: usize) {
+                debug(LogEvent::Message(format!(
+                    "Set search depth to {}",
+                    search_depth
+                )));
+                self.search_depth = search_depth;
+            }
+
+            /// Play with the depth limit, blunders and temperature of a preset.
+            pub fn set_difficulty(&mut self, level: DifficultyLevel) {
+                debug(LogEvent::Message(format!("Set difficulty to {:?}", level)));
+                self.difficulty = Difficulty::from_level(level);
+            }
+
+            /// Look at most `depth_limit` plies ahead, which also applies to
+            /// searches with a budget.
+            pub fn set_depth_limit(&mut self, depth_limit: Option<usize>) {
+                debug(LogEvent::Message(format!(
+                    "Set depth limit to {:?}",
+                    depth_limit
+                )));
+                self.difficulty.depth_limit = depth_limit;
+            }
+
+            /// Probability of playing a random move worse than the best one.
+            pub fn set_blunder_probability(&mut self, blunder_probability: f64) {
+                debug(LogEvent::Message(format!(
+                    "Set blunder probability to {}",
+                    blunder_probability
+                )));
+                self.difficulty.blunder_probability = blunder_probability;
+            }
+
+            /// Sample moves with the softmax of their values at `temperature`,
+            /// 0 always plays the best move.
+            pub fn set_temperature(&mut self, temperature: f64) {
+                debug(LogEvent::Message(format!(
+                    "Set temperature to {}",
+                    temperature
+                )));
+                self.difficulty.temperature = temperature;
+            }
+
+            /// Choose between moves with the same value with `tie_break`.
+            ///
+            /// Any other choice than the first move searches every move with
+            /// a full window, which takes longer than finding only the best.
+            pub fn set_tie_break(&mut self, tie_break: TieBreak) {
+                debug(LogEvent::Message(format!(
+                    "Set tie-break to {:?}",
+                    tie_break
+                )));
+                self.tie_break = tie_break;
+            }
+
+            /// Seed the random choices of the AI to make games reproducible.
+            pub fn set_seed(&mut self, seed: u64) {
+                debug(LogEvent::Message(format!("Set seed to {}", seed)));
+                self.rng = Rng::new(seed);
+            }
+
+            /// Depth up to which the tree is expanded in the background.
+            ///
+            /// The alpha-beta search does not rely on the stored tree, we only
+            /// need the direct children to track moves.
+            fn target_expanded_depth(&self) -> usize {
+                match self.search_mode {
+                    SearchMode::Minimax => self.max_expanded_depth,
+                    SearchMode::AlphaBeta => 1,
                 }
             }
 
             pub fn expand_one_level(&mut self) -> ExpandResult {
                 match self.cur_expanded_depth {
-                    x if x < self.max_expanded_depth => {
-                        self.expand_new_idx = self
-                            .tree_eval
+                    x if x < self.target_expanded_depth() => {
+                        self.tree_eval
                             .expand_and_get_children_idx(&self.expand_new_idx);
                         self.cur_expanded_depth += 1;
-                        console::log_1(
-                            &format!("Expanded level {}", self.cur_expanded_depth).into(),
-                        );
-                        match self.cur_expanded_depth < self.max_expanded_depth {
+                        // Besides the new children, a subtree which was kept
+                        // when a move was undone can have leafs at this depth
+                        self.expand_new_idx =
+                            self.get_unexpanded_leafs(self.last_move_idx, self.cur_expanded_depth);
+                        info(LogEvent::LevelExpanded {
+                            depth: self.cur_expanded_depth,
+                            transposition_hits: self.tree_eval.transposition_hits(),
+                            transposition_misses: self.tree_eval.transposition_misses(),
+                        });
+                        match self.cur_expanded_depth < self.target_expanded_depth() {
                             true => return ExpandResult::NotDone,
                             false => {
-                                console::log_1(&"Expansion done".into());
+                                info(LogEvent::ExpansionDone {
+                                    nodes: self.tree_eval.num_nodes(),
+                                });
                                 return ExpandResult::Done;
                             }
                         }
                     }
                     _ => {
-                        console::log_1(&"Expansion done".into());
+                        info(LogEvent::ExpansionDone {
+                            nodes: self.tree_eval.num_nodes(),
+                        });
                         return ExpandResult::Done;
                     }
                 }
             }
 
-            fn get_unexpanded_leafs(&self, start_idx: usize) -> Vec<usize> {
-                self.tree_eval
-                    .bfs_iter(start_idx)
-                    .filter(|&idx| {
-                        // Check first for the existence of childen - this will weed out
-                        // 90% of the nodes.
-                        if let Some(children) = self.tree_eval.children().get(idx) {
-                            if !children.is_empty() {
-                                // Skip node that already has children
-                                return false;
-                            }
+            /// Number of expanded positions which were already in the tree.
+            pub fn transposition_hits(&self) -> usize {
+                self.tree_eval.transposition_hits()
+            }
 
-                            // If children are empty, check if the position value
-                            // indicates a final state.
-                            if let Some(&worst_case_value) =
-                                self.tree_eval.worst_case_values().get(idx)
-                            {
-                                if worst_case_value != X_WIN_VALUE
-                                    && worst_case_value != -X_WIN_VALUE
-                                {
-                                    // No children and no terminal value -> this node
-                                    // should be expanded.
-                                    return true;
-                                }
-                            }
-                        }
+            /// Number of expanded positions which were added to the tree.
+            pub fn transposition_misses(&self) -> usize {
+                self.tree_eval.transposition_misses()
+            }
+
+            /// States `depth` moves below `start_idx` which still have to be
+            /// expanded.
+            fn get_unexpanded_leafs(&self, start_idx: usize, depth: usize) -> Vec<usize> {
+                let children = self.tree_eval.children();
+                let mut visited = vec![false; self.tree_eval.num_nodes()];
+                visited[start_idx] = true;
 
-                        // Fallback - either no children but terminal position value or
-                        // error in retrieving children / avg values.
-                        return false;
-                    })
+                // Shared children are only visited through their first parent
+                let mut level = vec![start_idx];
+                for _ in 0..depth {
+                    level = level
+                        .iter()
+                        .flat_map(|&idx| children[idx].iter().copied())
+                        .filter(|&child_idx| !std::mem::replace(&mut visited[child_idx], true))
+                        .collect();
+                }
+
+                level
+                    .into_iter()
+                    .filter(|&idx| children[idx].is_empty() && !self.tree_eval.is_final(idx))
                     .collect()
             }
 
-            pub fn track_move(&mut self, game_move: BoardMove) -> bool {
+            /// Play `game_move`, e.g. of the opponent.
+            ///
+            /// Illegal moves are rejected with the reason. Legal moves are
+            /// accepted even if the background expansion did not reach the
+            /// current position yet.
+            pub fn track_move(&mut self, game_move: BoardMove) -> Result<(), Error> {
+                if let Err(err) = self.validate_move(game_move) {
+                    warn(LogEvent::Message(err.to_string()));
+                    return Err(err);
+                }
+                if self.tree_eval.children()[self.last_move_idx].is_empty() {
+                    self.tree_eval.reexpand(self.last_move_idx);
+                }
+
+                debug(LogEvent::MoveTracked(game_move));
                 match self.identify_move(&game_move) {
-                    Some(idx) => {
-                        console::log_2(&"Tracked move".into(), &game_move.into());
-                        // Update tracking values in game interface
-                        self.last_move_idx = idx;
-                        self.cur_expanded_depth -= 1;
-
-                        // We want to expand only those leaf nodes that are reachable from
-                        // the new last move.
-                        self.expand_new_idx = self.get_unexpanded_leafs(self.last_move_idx);
-                        return true;
-                    }
+                    Some(idx) => self.move_to(idx, &game_move),
                     None => {
-                        console::log_2(&"Could not track move".into(), &game_move.into());
-                        return false;
+                        // Merged symmetries only keep a twin of the child
+                        let (idx, symmetry) = self
+                            .tree_eval
+                            .symmetric_child(self.last_move_idx, &game_move)
+                            .expect("Legal move should lead to a child or its twin");
+                        self.move_to(idx, &game_move);
+                        self.tree_eval
+                            .transform_subtree(self.last_move_idx, symmetry);
                     }
                 }
+
+                Ok(())
+            }
+
+            /// Check whether `game_move` can be played in the current position.
+            pub fn validate_move(&self, game_move: BoardMove) -> Result<(), Error> {
+                self.tree_eval.game_states()[self.last_move_idx]
+                    .validate_move(&game_move)
+                    .map_err(|reason| Error::IllegalMove(game_move, reason))
             }
 
-            pub fn get_best_move(&mut self) -> BoardMove {
-                // Evaluate value of all direct child states
-                self.tree_eval.evaluate_states(self.last_move_idx);
+            /// Play the best move and return it.
+            ///
+            /// Fails with `Error::GameOver` if the game has ended.
+            pub fn get_best_move(&mut self) -> Result<BoardMove, Error> {
+                self.prepare_search()?;
+                if let Some(book_move) = self.play_book_move() {
+                    return Ok(book_move);
+                }
+
+                let depth = match self.search_mode {
+                    SearchMode::Minimax => self.max_expanded_depth,
+                    SearchMode::AlphaBeta => self.search_depth,
+                };
+                if !self.difficulty.picks_best() || self.tie_break != TieBreak::First {
+                    return Ok(self.play_with_difficulty(depth)?.0);
+                }
 
-                let (best_idx, best_worst_case_value) = self.identify_best_move();
+                let start_ms = now_ms();
+                let limited_depth = self.difficulty.limit_depth(depth);
+                let (best_idx, best_worst_case_value) = match self.search_mode {
+                    // Only the depth-first search can stop above the expanded depth
+                    SearchMode::Minimax if limited_depth == depth => {
+                        // Evaluate value of all direct child states
+                        self.tree_eval.evaluate_states(self.last_move_idx);
+                        self.identify_best_move().ok_or(self.game_over())?
+                    }
+                    _ => {
+                        let (best_idx, best_value, nodes) = self
+                            .tree_eval
+                            .best_child_alpha_beta(self.last_move_idx, limited_depth)
+                            .ok_or(self.game_over())?;
+                        info(LogEvent::SearchFinished {
+                            depth: limited_depth,
+                            value: best_value,
+                            nodes,
+                            millis: now_ms() - start_ms,
+                        });
+                        (best_idx, best_value)
+                    }
+                };
+                debug(LogEvent::Message(format!(
+                    "Identified best move with worst_case_value {}",
+                    best_worst_case_value
+                )));
+
+                Ok(self.commit_best_move(best_idx))
+            }
+
+            /// Search the best move with iterative deepening for `budget_ms`.
+            ///
+            /// The move of the deepest finished iteration is played and returned
+            /// together with the depth that was reached.
+            pub fn get_best_move_within(&mut self, budget_ms: f64) -> Result<SearchResult, Error> {
+                self.get_best_move_with_budget(SearchBudget::Millis(budget_ms))
+            }
+
+            /// Search the best move with iterative deepening for `max_nodes`.
+            ///
+            /// In contrast to a time budget, the result is deterministic.
+            pub fn get_best_move_with_nodes(
+                &mut self,
+                max_nodes: usize,
+            ) -> Result<SearchResult, Error> {
+                self.get_best_move_with_budget(SearchBudget::Nodes(max_nodes))
+            }
+
+            fn get_best_move_with_budget(
+                &mut self,
+                budget: SearchBudget,
+            ) -> Result<SearchResult, Error> {
+                self.prepare_search()?;
+                if let Some(result) = self.book_result() {
+                    return Ok(result);
+                }
+
+                let start_ms = now_ms();
+                let max_depth = self.difficulty.limit_depth(MAX_SEARCH_DEPTH);
+                let (result, nodes) = self
+                    .tree_eval
+                    .best_child_deepening(self.last_move_idx, max_depth, budget)
+                    .ok_or(self.game_over())?;
+
+                info(LogEvent::SearchFinished {
+                    depth: result.depth,
+                    value: result.value,
+                    nodes,
+                    millis: now_ms() - start_ms,
+                });
+
+                if !self.difficulty.picks_best() || self.tie_break != TieBreak::First {
+                    // Values of all moves at the depth reached within the budget
+                    let (best_move, value) = self.play_with_difficulty(result.depth)?;
+                    return Ok(SearchResult {
+                        best_move,
+                        value,
+                        depth: result.depth,
+                    });
+                }
+
+                Ok(SearchResult {
+                    best_move: self.commit_best_move(result.best_child),
+                    value: result.value,
+                    depth: result.depth,
+                })
+            }
+
+            /// Make sure that there are moves to search.
+            ///
+            /// Positions which were not expanded in the background yet are
+            /// expanded now.
+            fn prepare_search(&mut self) -> Result<(), Error> {
+                if self.outcome().is_over() {
+                    return Err(self.game_over());
+                }
+                if self.tree_eval.children()[self.last_move_idx].is_empty() {
+                    self.tree_eval.reexpand(self.last_move_idx);
+                }
+                Ok(())
+            }
+
+            fn game_over(&self) -> Error {
+                Error::GameOver(self.outcome())
+            }
+
+            /// Play the move of the opening book for the current position.
+            ///
+            /// Returns `None` if the book is disabled or has no move for it.
+            ///
+            /// The book is skipped below the perfect difficulty since it would
+            /// play better than the search.
+            fn play_book_move(&mut self) -> Option<BoardMove> {
+                if !self.use_book || self.book.is_empty() || self.difficulty != Difficulty::default()
+                {
+                    return None;
+                }
+
+                let state = &self.tree_eval.game_states()[self.last_move_idx];
+                let book_move = self.book.find_move(state, <$game_state>::to_notation, None)?;
+
+                debug(LogEvent::BookMovePlayed(book_move));
+                self.track_move(book_move).ok().map(|_| book_move)
+            }
+
+            /// Search result of a book move, which was not searched at all.
+            fn book_result(&mut self) -> Option<SearchResult> {
+                self.play_book_move().map(|best_move| SearchResult {
+                    best_move,
+                    value: 0,
+                    depth: 0,
+                })
+            }
+
+            /// Play a move chosen by the difficulty and the tie-break out of the
+            /// values of all moves searched with at most `depth` plies.
+            ///
+            /// Returns the move together with its value.
+            fn play_with_difficulty(&mut self, depth: usize) -> Result<(BoardMove, i32), Error> {
+                let depth = self.difficulty.limit_depth(depth);
+                let values = self
+                    .tree_eval
+                    .child_values_alpha_beta(self.last_move_idx, depth)
+                    .ok_or(self.game_over())?;
+                // If the last turn was O, the next is X and we want maximum values
+                let maximizing = self.tree_eval.game_states()[self.last_move_idx].side() == Cell::O;
+                let pos = match self.difficulty.pick(&values, maximizing, &mut self.rng) {
+                    Some(pos) => pos,
+                    None => {
+                        let state = &self.tree_eval.game_states()[self.last_move_idx];
+                        let moves: Vec<BoardMove> = self.tree_eval.children()[self.last_move_idx]
+                            .iter()
+                            .map(|&child_idx| {
+                                self.tree_eval
+                                    .child_move(self.last_move_idx, child_idx)
+                                    .expect("Child move")
+                            })
+                            .collect();
+                        self.tie_break
+                            .choose(
+                                &best_positions(&values, maximizing),
+                                &moves,
+                                state.board_size(),
+                                &mut self.rng,
+                            )
+                            .ok_or(self.game_over())?
+                    }
+                };
+                debug(LogEvent::Message(format!(
+                    "Picked move with value {} at depth {}",
+                    values[pos], depth
+                )));
+
+                let idx = self.tree_eval.children()[self.last_move_idx][pos];
+                Ok((self.commit_best_move(idx), values[pos]))
+            }
+
+            /// Play the move to `best_idx` and return it.
+            fn commit_best_move(&mut self, best_idx: usize) -> BoardMove {
                 let best_move = self
                     .tree_eval
-                    .game_states()
-                    .get(best_idx)
-                    .expect("Best state")
-                    .last_move();
-
-                console::log_1(
-                    &format!(
-                        "Identified best move {:?} with worst_case_value {}",
-                        &best_move, best_worst_case_value
-                    )
-                    .into(),
-                );
+                    .child_move(self.last_move_idx, best_idx)
+                    .expect("Best move");
+                debug(LogEvent::MovePlayed(best_move));
+                self.move_to(best_idx, &best_move);
+
+                best_move
+            }
+
+            /// Continue the game with `game_move` to the child state `idx` of
+            /// the last move.
+            ///
+            /// States which are not reachable from `idx` anymore are dropped
+            /// from the tree to reclaim their memory. Only the line of played
+            /// moves is kept so that moves can be taken back.
+            fn move_to(&mut self, idx: usize, game_move: &BoardMove) {
+                let num_nodes = self.tree_eval.num_nodes();
+                self.tree_eval.attach_to_parent(idx, self.last_move_idx);
+                let new_idx = self.tree_eval.prune(idx);
+                debug(LogEvent::TreePruned {
+                    nodes_before: num_nodes,
+                    nodes_after: self.tree_eval.num_nodes(),
+                });
+
+                // Moves can only be redone as long as the same line is played
+                match self.redo_moves.last() {
+                    Some(redo_move) if redo_move == game_move => {
+                        self.redo_moves.pop();
+                    }
+                    _ => self.redo_moves.clear(),
+                }
 
                 // Update tracking values in game interface
-                self.last_move_idx = best_idx;
-                self.cur_expanded_depth -= 1;
+                self.last_move_idx = new_idx[idx].expect("Kept state");
+                self.cur_expanded_depth = self.cur_expanded_depth.saturating_sub(1);
 
                 // We want to expand only those leaf nodes that are reachable from
                 // the new last move.
-                self.expand_new_idx = self.get_unexpanded_leafs(self.last_move_idx);
+                self.expand_new_idx =
+                    self.get_unexpanded_leafs(self.last_move_idx, self.cur_expanded_depth);
+            }
 
-                best_move
+            /// Take back the last move.
+            ///
+            /// The tree below the move is kept, only the alternatives to it are
+            /// expanded again. Returns false at the start of the game.
+            pub fn undo_move(&mut self) -> bool {
+                let parent_idx = self.tree_eval.parent()[self.last_move_idx];
+                if parent_idx == self.last_move_idx {
+                    warn(LogEvent::Message("No move to undo".to_owned()));
+                    return false;
+                }
+
+                let undone_move = self
+                    .tree_eval
+                    .child_move(parent_idx, self.last_move_idx)
+                    .expect("Undone move");
+                debug(LogEvent::MoveUndone(undone_move));
+                self.redo_moves.push(undone_move);
+
+                // Only the alternatives to the undone move are expanded next.
+                // The pending leafs below it are deeper and join the expansion
+                // once it reaches their depth.
+                self.tree_eval.reexpand(parent_idx);
+                self.last_move_idx = parent_idx;
+                self.cur_expanded_depth = 1;
+                self.expand_new_idx = self.get_unexpanded_leafs(parent_idx, 1);
+
+                true
+            }
+
+            /// Play the last move which was taken back again.
+            ///
+            /// Returns false if there is no move to redo.
+            pub fn redo_move(&mut self) -> bool {
+                match self.redo_moves.last() {
+                    Some(&redo_move) => self.track_move(redo_move).is_ok(),
+                    None => {
+                        warn(LogEvent::Message("No move to redo".to_owned()));
+                        false
+                    }
+                }
+            }
+
+            /// Score every legal move and find the principal variation without
+            /// playing a move.
+            ///
+            /// Returns `{ moves: [{ boardMove, score }], principalVariation,
+            /// value, depth }`.
+            #[wasm_bindgen(js_name = analyze)]
+            pub fn analyze_js(&mut self) -> Result<JsValue, Error> {
+                self.analyze().to_js_value()
+            }
+
+            /// Outcome of the game as `{ kind, winner }` object.
+            #[wasm_bindgen(js_name = outcome)]
+            pub fn outcome_js(&self) -> Result<JsValue, Error> {
+                self.outcome().to_js_value()
+            }
+
+            /// History of the game in the PGN-like text format of `GameRecord`.
+            pub fn record_text(&self) -> String {
+                self.to_record().to_text()
+            }
+
+            /// History of the game as JSON.
+            pub fn record_json(&self) -> String {
+                self.to_record().to_json()
+            }
+
+            /// Replay a record in the text format or as JSON.
+            ///
+            /// The game is left unchanged if the record cannot be replayed.
+            pub fn load_record(&mut self, record: &str) -> Result<(), Error> {
+                GameRecord::parse(record).and_then(|record| self.replay_record(&record))
+            }
+
+            /// Current position in the notation of `Board::from_notation`.
+            pub fn to_notation(&self) -> String {
+                self.tree_eval.game_states()[self.last_move_idx].to_notation()
+            }
+
+            /// Play moves from the opening book before searching.
+            pub fn set_use_book(&mut self, use_book: bool) {
+                debug(LogEvent::Message(format!("Set use of book to {}", use_book)));
+                self.use_book = use_book;
+            }
+
+            /// Replace the opening book by one in the text format of
+            /// `OpeningBook`.
+            pub fn load_book(&mut self, book: &str) -> Result<(), Error> {
+                self.book = Rc::new(OpeningBook::parse(book)?);
+                Ok(())
             }
 
             pub fn reset(&mut self) {
-                console::log_1(&"Resetting game interface".into());
-                self.tree_eval = TreeEvaluator::new_with_default();
+                info(LogEvent::Message("Resetting game interface".to_owned()));
+                self.tree_eval = TreeEvaluator::new(self.init_state.clone());
+                self.tree_eval.set_heuristic(self.heuristic);
+                self.tree_eval.set_merge_symmetries(self.merge_symmetries);
+                self.redo_moves.clear();
                 self.last_move_idx = 0;
                 self.expand_new_idx = vec![0];
                 self.cur_expanded_depth = 0;
@@ -144,41 +641,19 @@ macro_rules! gen_game_if_impl {
             }
 
             fn identify_move(&self, game_move: &BoardMove) -> Option<usize> {
-                let direct_children = self
-                    .tree_eval
-                    .children()
-                    .get(self.last_move_idx)
-                    .expect("Direct children");
-
-                let game_states = direct_children.iter().map(|&child_idx| {
-                    self.tree_eval
-                        .game_states()
-                        .get(child_idx)
-                        .expect("Child game state")
-                });
+                let direct_children = self.tree_eval.children().get(self.last_move_idx)?;
 
-                for (&child_idx, game_state) in direct_children.iter().zip(game_states) {
-                    if *game_move == game_state.last_move() {
-                        return Some(child_idx);
-                    }
-                }
-
-                None
+                direct_children.iter().copied().find(|&child_idx| {
+                    self.tree_eval.child_move(self.last_move_idx, child_idx) == Some(*game_move)
+                })
             }
 
-            fn identify_best_move(&self) -> (usize, i32) {
+            /// Child of the last move with the best value, `None` without
+            /// children.
+            fn identify_best_move(&self) -> Option<(usize, i32)> {
                 // Select child state with highest value for `side`
-                let last_state = self
-                    .tree_eval
-                    .game_states()
-                    .get(self.last_move_idx)
-                    .expect("Last state");
-
-                let direct_children_idx = self
-                    .tree_eval
-                    .children()
-                    .get(self.last_move_idx)
-                    .expect("Direct children");
+                let last_state = self.tree_eval.game_states().get(self.last_move_idx)?;
+                let direct_children_idx = self.tree_eval.children().get(self.last_move_idx)?;
 
                 let worst_case_values = direct_children_idx
                     .iter()
@@ -188,7 +663,8 @@ macro_rules! gen_game_if_impl {
                     match last_state.side() {
                         Cell::O => {
                             direct_children_idx.iter().zip(worst_case_values).fold(
-                                (None, -X_WIN_VALUE),
+                                // Even a lost child is better than none
+                                (None, i32::MIN),
                                 |(best_idx, best_worst_case_value),
                                  (&child_idx, &child_worst_case_value)| {
                                     // If the last turn was O, the next is X and we want
@@ -202,7 +678,7 @@ macro_rules! gen_game_if_impl {
                         }
                         Cell::X => {
                             direct_children_idx.iter().zip(worst_case_values).fold(
-                                (None, X_WIN_VALUE),
+                                (None, i32::MAX),
                                 |(best_idx, best_worst_case_value),
                                  (&child_idx, &child_worst_case_value)| {
                                     // If the last turn was O, the next is X and we want
@@ -217,16 +693,132 @@ macro_rules! gen_game_if_impl {
                         Cell::Empty => (None, 0),
                     };
 
-                (
-                    best_idx.expect("Should have found a best index"),
-                    best_worst_case_value,
-                )
+                best_idx.map(|best_idx| (best_idx, best_worst_case_value))
+            }
+        }
+
+        impl $game_if {
+            /// Start the game at `init_state`, which is also restored on reset.
+            fn with_init_state(init_state: $game_state) -> Self {
+                set_panic_hook();
+                info(LogEvent::Message(
+                    "Initialized a new GameInterface".to_owned(),
+                ));
+                let mut tree_eval = TreeEvaluator::new(init_state.clone());
+                tree_eval.set_heuristic($heuristic);
+
+                Self {
+                    tree_eval,
+                    init_state,
+                    last_move_idx: 0,
+                    redo_moves: vec![],
+                    expand_new_idx: vec![0],
+                    cur_expanded_depth: 0,
+                    max_expanded_depth: $max_depth,
+                    search_mode: $search_mode,
+                    search_depth: $search_depth,
+                    heuristic: $heuristic,
+                    merge_symmetries: false,
+                    book: Rc::new($book),
+                    use_book: true,
+                    difficulty: Difficulty::default(),
+                    tie_break: TieBreak::First,
+                    rng: Rng::default(),
+                }
+            }
+
+            /// Analysis of the current position with the configured search.
+            ///
+            /// Minimax uses the values of the expanded tree while alpha-beta
+            /// searches `search_depth` plies.
+            pub fn analyze(&mut self) -> Analysis {
+                let analysis = match self.search_mode {
+                    SearchMode::Minimax => self.tree_eval.analyze_minimax(self.last_move_idx),
+                    SearchMode::AlphaBeta => self
+                        .tree_eval
+                        .analyze_alpha_beta(self.last_move_idx, self.search_depth),
+                };
+
+                // Final positions have no moves to analyze
+                analysis.unwrap_or(Analysis {
+                    moves: vec![],
+                    principal_variation: vec![],
+                    value: self.tree_eval.game_states()[self.last_move_idx].position_value(),
+                    depth: 0,
+                })
+            }
+
+            /// Outcome of the game at the last tracked move.
+            pub fn outcome(&self) -> GameOutcome {
+                self.tree_eval
+                    .game_states()
+                    .get(self.last_move_idx)
+                    .expect("Last state")
+                    .outcome()
+            }
+
+            /// Record of the moves from the initial state to the last move.
+            pub fn to_record(&self) -> GameRecord {
+                let mut record = self.init_state.new_record();
+                let mut idx = self.last_move_idx;
+                loop {
+                    let parent_idx = self.tree_eval.parent()[idx];
+                    if parent_idx == idx {
+                        break;
+                    }
+                    record.moves.push(
+                        self.tree_eval
+                            .child_move(parent_idx, idx)
+                            .expect("Played move"),
+                    );
+                    idx = parent_idx;
+                }
+                record.moves.reverse();
+                record.result = self.outcome();
+
+                record
+            }
+
+            /// Restart at the start of `record` and track all of its moves.
+            ///
+            /// The search settings are kept. The game is only replaced if all
+            /// moves are legal and lead to the result of the record.
+            pub fn replay_record(&mut self, record: &GameRecord) -> Result<(), Error> {
+                let mut replayed =
+                    Self::with_init_state(<$game_state>::from_record_start(record)?);
+                replayed.search_mode = self.search_mode;
+                replayed.search_depth = self.search_depth;
+                replayed.heuristic = self.heuristic;
+                replayed.tree_eval.set_heuristic(self.heuristic);
+                replayed.merge_symmetries = self.merge_symmetries;
+                replayed
+                    .tree_eval
+                    .set_merge_symmetries(self.merge_symmetries);
+                replayed.book = self.book.clone();
+                replayed.use_book = self.use_book;
+                replayed.difficulty = self.difficulty;
+                replayed.tie_break = self.tie_break;
+                replayed.rng = self.rng.clone();
+
+                for &board_move in record.moves.iter() {
+                    replayed.expand_one_level();
+                    replayed.track_move(board_move)?;
+                }
+                if record.result.is_over() && replayed.outcome() != record.result {
+                    return Err(Error::InvalidRecord(format!(
+                        "Moves of the record do not lead to {:?}",
+                        record.result
+                    )));
+                }
+
+                *self = replayed;
+                Ok(())
             }
         }
 
         impl Default for $game_if {
             fn default() -> Self {
-                Self::new()
+                Self::with_init_state(<$game_state>::default())
             }
         }
     };
@@ -235,21 +827,545 @@ macro_rules! gen_game_if_impl {
 #[wasm_bindgen]
 pub struct T3GameInterface {
     tree_eval: TreeEvaluator<T3GameState>,
+    init_state: T3GameState,
     last_move_idx: usize,
+    /// Moves which were taken back, the last one is redone first
+    redo_moves: Vec<BoardMove>,
     expand_new_idx: Vec<usize>,
     cur_expanded_depth: usize,
     max_expanded_depth: usize,
+    search_mode: SearchMode,
+    search_depth: usize,
+    heuristic: Heuristic,
+    merge_symmetries: bool,
+    /// Moves played instead of searching early in the game
+    book: Rc<OpeningBook>,
+    use_book: bool,
+    difficulty: Difficulty,
+    tie_break: TieBreak,
+    /// Source of the random choices of the difficulty and the tie-break
+    rng: Rng,
 }
 
-gen_game_if_impl!(T3GameInterface, 9);
+gen_game_if_impl!(
+    T3GameInterface,
+    T3GameState,
+    9,
+    SearchMode::Minimax,
+    9,
+    Heuristic::WinLoss,
+    OpeningBook::new()
+);
 
 #[wasm_bindgen]
 pub struct FiarGameInterface {
     tree_eval: TreeEvaluator<FiarGameState>,
+    init_state: FiarGameState,
     last_move_idx: usize,
+    /// Moves which were taken back, the last one is redone first
+    redo_moves: Vec<BoardMove>,
     expand_new_idx: Vec<usize>,
     cur_expanded_depth: usize,
     max_expanded_depth: usize,
+    search_mode: SearchMode,
+    search_depth: usize,
+    heuristic: Heuristic,
+    merge_symmetries: bool,
+    /// Moves played instead of searching early in the game
+    book: Rc<OpeningBook>,
+    use_book: bool,
+    difficulty: Difficulty,
+    tie_break: TieBreak,
+    /// Source of the random choices of the difficulty and the tie-break
+    rng: Rng,
+}
+
+gen_game_if_impl!(
+    FiarGameInterface,
+    FiarGameState,
+    6,
+    SearchMode::AlphaBeta,
+    10,
+    Heuristic::Lines,
+    OpeningBook::fiar()
+);
+
+#[wasm_bindgen]
+impl FiarGameInterface {
+    /// Play the side to move into `col` and return the move where it landed.
+    pub fn drop_in_column(&mut self, col: u32) -> Result<BoardMove, Error> {
+        let board_move = self.tree_eval.game_states()[self.last_move_idx].drop_in_column(col)?;
+        self.track_move(board_move)?;
+        Ok(board_move)
+    }
+}
+
+/// Interface for (m,n,k)-games like gomoku or connect-four variants.
+#[wasm_bindgen]
+pub struct ConnectNGameInterface {
+    tree_eval: TreeEvaluator<ConnectNGameState>,
+    init_state: ConnectNGameState,
+    last_move_idx: usize,
+    /// Moves which were taken back, the last one is redone first
+    redo_moves: Vec<BoardMove>,
+    expand_new_idx: Vec<usize>,
+    cur_expanded_depth: usize,
+    max_expanded_depth: usize,
+    search_mode: SearchMode,
+    search_depth: usize,
+    heuristic: Heuristic,
+    merge_symmetries: bool,
+    /// Moves played instead of searching early in the game
+    book: Rc<OpeningBook>,
+    use_book: bool,
+    difficulty: Difficulty,
+    tie_break: TieBreak,
+    /// Source of the random choices of the difficulty and the tie-break
+    rng: Rng,
+}
+
+#[wasm_bindgen]
+impl ConnectNGameInterface {
+    /// Play `num_winner` in a row on a board of `height` x `width` cells.
+    ///
+    /// With `gravity`, pieces drop to the bottom of the columns. Otherwise,
+    /// they can be placed on any empty cell.
+    ///
+    /// Fails if the board is empty or `num_winner` in a row cannot fit on it.
+    pub fn new(
+        height: u32,
+        width: u32,
+        num_winner: i32,
+        gravity: bool,
+    ) -> Result<ConnectNGameInterface, Error> {
+        ConnectNGameState::new_game(height, width, num_winner, gravity).map(Self::with_init_state)
+    }
+
+    /// Start the game at a position in the notation of
+    /// `Board::from_notation`, which also defines the size of the board.
+    pub fn from_notation(
+        notation: &str,
+        num_winner: i32,
+        gravity: bool,
+    ) -> Result<ConnectNGameInterface, Error> {
+        ConnectNGameState::from_notation(notation, num_winner, gravity).map(Self::with_init_state)
+    }
+}
+
+// Large boards have too many moves for a full tree, so only the direct
+// children are stored and the rest is searched with alpha-beta.
+gen_game_if_impl!(
+    @shared ConnectNGameInterface,
+    ConnectNGameState,
+    2,
+    SearchMode::AlphaBeta,
+    4,
+    Heuristic::Lines,
+    OpeningBook::new()
+);
+
+macro_rules! gen_mcts_if_impl {
+    ( $game_if:ty, $game_state:ty, $slice_ms:expr, $max_nodes:expr, $book:expr ) => {
+        #[wasm_bindgen]
+        impl $game_if {
+            pub fn new() -> Self {
+                Self::with_init_state(<$game_state>::default())
+            }
+
+            /// Start the game at a position in the notation of
+            /// `Board::from_notation`, e.g. to load a puzzle.
+            pub fn from_notation(notation: &str) -> Result<$game_if, Error> {
+                <$game_state>::from_notation(notation).map(Self::with_init_state)
+            }
+
+            /// Run MCTS iterations for one time slice.
+            ///
+            /// This has the same signature as for the tree-based interfaces so
+            /// that the worker can schedule it the same way. Iterations continue
+            /// until the node limit is reached or the game is over.
+            pub fn expand_one_level(&mut self) -> ExpandResult {
+                if self.mcts.num_nodes() >= $max_nodes || self.outcome().is_over() {
+                    info(LogEvent::ExpansionDone {
+                        nodes: self.mcts.num_nodes(),
+                    });
+                    return ExpandResult::Done;
+                }
+
+                let num_iterations = self.mcts.run_for(self.last_move_idx, $slice_ms);
+                info(LogEvent::MctsIterations {
+                    iterations: num_iterations,
+                    visits: self.mcts.visits()[self.last_move_idx],
+                    nodes: self.mcts.num_nodes(),
+                });
+
+                ExpandResult::NotDone
+            }
+
+            /// Play `game_move`, e.g. of the opponent.
+            ///
+            /// Illegal moves are rejected with the reason.
+            pub fn track_move(&mut self, game_move: BoardMove) -> Result<(), Error> {
+                if let Err(err) = self.validate_move(game_move) {
+                    warn(LogEvent::Message(err.to_string()));
+                    return Err(err);
+                }
+                self.mcts.expand_state(self.last_move_idx);
+
+                let idx = self.mcts.children()[self.last_move_idx]
+                    .iter()
+                    .copied()
+                    .find(|&child_idx| self.mcts.game_states()[child_idx].last_move() == game_move)
+                    .expect("Legal move should lead to a child");
+                debug(LogEvent::MoveTracked(game_move));
+                self.move_to(idx, &game_move);
+
+                Ok(())
+            }
+
+            /// Check whether `game_move` can be played in the current position.
+            pub fn validate_move(&self, game_move: BoardMove) -> Result<(), Error> {
+                self.mcts.game_states()[self.last_move_idx]
+                    .validate_move(&game_move)
+                    .map_err(|reason| Error::IllegalMove(game_move, reason))
+            }
+
+            /// Play the most visited move and return it.
+            ///
+            /// Fails with `Error::GameOver` if the game has ended.
+            pub fn get_best_move(&mut self) -> Result<BoardMove, Error> {
+                self.check_not_over()?;
+                if let Some(book_move) = self.play_book_move() {
+                    return Ok(book_move);
+                }
+
+                if self.mcts.children()[self.last_move_idx].is_empty() {
+                    // Nothing was searched yet, we need at least one slice
+                    self.mcts.run_for(self.last_move_idx, $slice_ms);
+                }
+
+                let best_idx = self
+                    .mcts
+                    .best_child(self.last_move_idx)
+                    .ok_or(Error::GameOver(self.outcome()))?;
+                let best_move = self.mcts.game_states()[best_idx].last_move();
+
+                debug(LogEvent::Message(format!(
+                    "Identified best move {:?} with {} visits and win rate {:.3}",
+                    &best_move,
+                    self.mcts.visits()[best_idx],
+                    self.mcts.win_rate(best_idx)
+                )));
+
+                self.move_to(best_idx, &best_move);
+                Ok(best_move)
+            }
+
+            fn check_not_over(&self) -> Result<(), Error> {
+                match self.outcome() {
+                    outcome if outcome.is_over() => Err(Error::GameOver(outcome)),
+                    _ => Ok(()),
+                }
+            }
+
+            /// Continue the game with `game_move` to the child state `idx`.
+            fn move_to(&mut self, idx: usize, game_move: &BoardMove) {
+                // Moves can only be redone as long as the same line is played
+                match self.redo_moves.last() {
+                    Some(redo_move) if redo_move == game_move => {
+                        self.redo_moves.pop();
+                    }
+                    _ => self.redo_moves.clear(),
+                }
+
+                self.last_move_idx = idx;
+            }
+
+            /// Take back the last move, the statistics of the tree are kept.
+            ///
+            /// Returns false at the start of the game.
+            pub fn undo_move(&mut self) -> bool {
+                if self.last_move_idx == 0 {
+                    warn(LogEvent::Message("No move to undo".to_owned()));
+                    return false;
+                }
+
+                let undone_move = self.mcts.game_states()[self.last_move_idx].last_move();
+                debug(LogEvent::MoveUndone(undone_move));
+                self.redo_moves.push(undone_move);
+                self.last_move_idx = self.mcts.parent()[self.last_move_idx];
+
+                true
+            }
+
+            /// Play the last move which was taken back again.
+            ///
+            /// Returns false if there is no move to redo.
+            pub fn redo_move(&mut self) -> bool {
+                match self.redo_moves.last() {
+                    Some(&redo_move) => self.track_move(redo_move).is_ok(),
+                    None => {
+                        warn(LogEvent::Message("No move to redo".to_owned()));
+                        false
+                    }
+                }
+            }
+
+            /// Run iterations for `budget_ms` before playing the best move.
+            pub fn get_best_move_within(&mut self, budget_ms: f64) -> Result<SearchResult, Error> {
+                self.check_not_over()?;
+                if let Some(result) = self.book_result() {
+                    return Ok(result);
+                }
+
+                self.mcts.run_for(self.last_move_idx, budget_ms);
+                self.get_search_result()
+            }
+
+            /// Run `max_nodes` iterations before playing the best move.
+            pub fn get_best_move_with_nodes(
+                &mut self,
+                max_nodes: usize,
+            ) -> Result<SearchResult, Error> {
+                self.check_not_over()?;
+                if let Some(result) = self.book_result() {
+                    return Ok(result);
+                }
+
+                self.mcts.run_iterations(self.last_move_idx, max_nodes);
+                self.get_search_result()
+            }
+
+            /// Play the move of the opening book for the current position.
+            fn play_book_move(&mut self) -> Option<BoardMove> {
+                if !self.use_book || self.book.is_empty() {
+                    return None;
+                }
+
+                let state = &self.mcts.game_states()[self.last_move_idx];
+                let book_move = self
+                    .book
+                    .find_move(state, <$game_state>::to_notation, None)?;
+                debug(LogEvent::BookMovePlayed(book_move));
+                self.track_move(book_move).ok().map(|_| book_move)
+            }
+
+            /// Search result of a book move, which was not searched at all.
+            fn book_result(&mut self) -> Option<SearchResult> {
+                self.play_book_move().map(|best_move| SearchResult {
+                    best_move,
+                    value: 0,
+                    depth: 0,
+                })
+            }
+
+            /// Play the best move and report how deep the most visited line goes.
+            fn get_search_result(&mut self) -> Result<SearchResult, Error> {
+                // Budgets too small for one iteration did not expand the root
+                self.mcts.expand_state(self.last_move_idx);
+                let mut depth = 0;
+                let mut idx = self.last_move_idx;
+                while let Some(child_idx) = self.mcts.best_child(idx) {
+                    depth += 1;
+                    idx = child_idx;
+                }
+
+                let best_idx = self
+                    .mcts
+                    .best_child(self.last_move_idx)
+                    .ok_or(Error::GameOver(self.outcome()))?;
+                let value = (self.mcts.win_rate(best_idx) * 100.0).round() as i32;
+
+                Ok(SearchResult {
+                    best_move: self.get_best_move()?,
+                    value,
+                    depth,
+                })
+            }
+
+            /// Outcome of the game as `{ kind, winner }` object.
+            #[wasm_bindgen(js_name = outcome)]
+            pub fn outcome_js(&self) -> Result<JsValue, Error> {
+                self.outcome().to_js_value()
+            }
+
+            /// History of the game in the PGN-like text format of `GameRecord`.
+            pub fn record_text(&self) -> String {
+                self.to_record().to_text()
+            }
+
+            /// History of the game as JSON.
+            pub fn record_json(&self) -> String {
+                self.to_record().to_json()
+            }
+
+            /// Replay a record in the text format or as JSON.
+            ///
+            /// The game is left unchanged if the record cannot be replayed.
+            pub fn load_record(&mut self, record: &str) -> Result<(), Error> {
+                GameRecord::parse(record).and_then(|record| self.replay_record(&record))
+            }
+
+            /// Current position in the notation of `Board::from_notation`.
+            pub fn to_notation(&self) -> String {
+                self.mcts.game_states()[self.last_move_idx].to_notation()
+            }
+
+            /// Play moves from the opening book before searching.
+            pub fn set_use_book(&mut self, use_book: bool) {
+                debug(LogEvent::Message(format!(
+                    "Set use of book to {}",
+                    use_book
+                )));
+                self.use_book = use_book;
+            }
+
+            /// Replace the opening book by one in the text format of
+            /// `OpeningBook`.
+            pub fn load_book(&mut self, book: &str) -> Result<(), Error> {
+                self.book = Rc::new(OpeningBook::parse(book)?);
+                Ok(())
+            }
+
+            /// Seed the random playouts to make games reproducible.
+            pub fn set_seed(&mut self, seed: u64) {
+                debug(LogEvent::Message(format!("Set seed to {}", seed)));
+                self.seed = Some(seed);
+                self.mcts.set_seed(seed);
+            }
+
+            pub fn reset(&mut self) {
+                info(LogEvent::Message("Resetting game interface".to_owned()));
+                self.mcts = MctsEvaluator::new(self.init_state.clone());
+                if let Some(seed) = self.seed {
+                    self.mcts.set_seed(seed);
+                }
+                self.last_move_idx = 0;
+                self.redo_moves.clear();
+            }
+        }
+
+        impl $game_if {
+            /// Start the game at `init_state`, which is also restored on reset.
+            fn with_init_state(init_state: $game_state) -> Self {
+                set_panic_hook();
+                info(LogEvent::Message(
+                    "Initialized a new MCTS GameInterface".to_owned(),
+                ));
+                Self {
+                    mcts: MctsEvaluator::new(init_state.clone()),
+                    init_state,
+                    last_move_idx: 0,
+                    redo_moves: vec![],
+                    book: Rc::new($book),
+                    use_book: true,
+                    seed: None,
+                }
+            }
+
+            /// Outcome of the game at the last tracked move.
+            pub fn outcome(&self) -> GameOutcome {
+                self.mcts.game_states()[self.last_move_idx].outcome()
+            }
+
+            /// Record of the moves from the initial state to the last move.
+            pub fn to_record(&self) -> GameRecord {
+                let mut record = self.init_state.new_record();
+                let mut idx = self.last_move_idx;
+                while idx != 0 {
+                    record.moves.push(self.mcts.game_states()[idx].last_move());
+                    idx = self.mcts.parent()[idx];
+                }
+                record.moves.reverse();
+                record.result = self.outcome();
+
+                record
+            }
+
+            /// Restart at the start of `record` and track all of its moves.
+            ///
+            /// The game is only replaced if all moves are legal and lead to the
+            /// result of the record.
+            pub fn replay_record(&mut self, record: &GameRecord) -> Result<(), Error> {
+                let mut replayed = Self::with_init_state(<$game_state>::from_record_start(record)?);
+                replayed.book = self.book.clone();
+                replayed.use_book = self.use_book;
+                if let Some(seed) = self.seed {
+                    replayed.set_seed(seed);
+                }
+
+                for &board_move in record.moves.iter() {
+                    replayed.track_move(board_move)?;
+                }
+                if record.result.is_over() && replayed.outcome() != record.result {
+                    return Err(Error::InvalidRecord(format!(
+                        "Moves of the record do not lead to {:?}",
+                        record.result
+                    )));
+                }
+
+                *self = replayed;
+                Ok(())
+            }
+        }
+
+        impl Default for $game_if {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+    };
+}
+
+#[wasm_bindgen]
+pub struct T3MctsInterface {
+    mcts: MctsEvaluator<T3GameState>,
+    init_state: T3GameState,
+    last_move_idx: usize,
+    /// Moves which were taken back, the last one is redone first
+    redo_moves: Vec<BoardMove>,
+    /// Moves played instead of searching early in the game
+    book: Rc<OpeningBook>,
+    use_book: bool,
+    /// Seed of the random playouts, which is restored on reset
+    seed: Option<u64>,
+}
+
+gen_mcts_if_impl!(
+    T3MctsInterface,
+    T3GameState,
+    50.0,
+    500_000,
+    OpeningBook::new()
+);
+
+#[wasm_bindgen]
+pub struct FiarMctsInterface {
+    mcts: MctsEvaluator<FiarGameState>,
+    init_state: FiarGameState,
+    last_move_idx: usize,
+    /// Moves which were taken back, the last one is redone first
+    redo_moves: Vec<BoardMove>,
+    /// Moves played instead of searching early in the game
+    book: Rc<OpeningBook>,
+    use_book: bool,
+    /// Seed of the random playouts, which is restored on reset
+    seed: Option<u64>,
 }
 
-gen_game_if_impl!(FiarGameInterface, 6);
+gen_mcts_if_impl!(
+    FiarMctsInterface,
+    FiarGameState,
+    50.0,
+    1_000_000,
+    OpeningBook::fiar()
+);
+
+#[wasm_bindgen]
+impl FiarMctsInterface {
+    /// Play the side to move into `col` and return the move where it landed.
+    pub fn drop_in_column(&mut self, col: u32) -> Result<BoardMove, Error> {
+        let board_move = self.mcts.game_states()[self.last_move_idx].drop_in_column(col)?;
+        self.track_move(board_move)?;
+        Ok(board_move)
+    }
+}
@@ -0,0 +1,390 @@
+use crate::{Board, BoardMove, Cell, Error, GameOutcome};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// Game which is played in a `GameRecord`.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum GameKind {
+    TicTacToe,
+    FourInARow,
+    ConnectN,
+}
+
+impl GameKind {
+    /// Board height, width, win length and gravity of games with fixed rules.
+    fn fixed_rules(&self) -> Option<(u32, u32, i32, bool)> {
+        match self {
+            GameKind::TicTacToe => Some((3, 3, 3, false)),
+            GameKind::FourInARow => Some((6, 7, 4, true)),
+            GameKind::ConnectN => None,
+        }
+    }
+}
+
+/// Complete history of a game which can be replayed by a game interface.
+///
+/// The text format is inspired by [PGN](https://en.wikipedia.org/wiki/Portable_Game_Notation):
+///
+/// ```text
+/// [Game "FourInARow"]
+/// [Size "6x7"]
+/// [WinLength "4"]
+/// [Gravity "true"]
+/// [Result "1-0"]
+///
+/// 1. 5,3 5,2 2. 4,3 4,2 3. 3,3 3,2 4. 2,3 1-0
+/// ```
+///
+/// Moves are given as `row,col` and alternate between the sides. Games which
+/// did not start on an empty board carry their start position in the
+/// notation of `Board::from_notation` as `FEN` tag.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GameRecord {
+    pub kind: GameKind,
+    pub height: u32,
+    pub width: u32,
+    pub num_winner: i32,
+    pub gravity: bool,
+    /// Start position if the game did not start on an empty board
+    pub start: Option<String>,
+    pub moves: Vec<BoardMove>,
+    pub result: GameOutcome,
+}
+
+impl GameRecord {
+    /// Record without moves, `start` is dropped if it is the empty board.
+    pub fn new(
+        kind: GameKind,
+        height: u32,
+        width: u32,
+        num_winner: i32,
+        gravity: bool,
+        start: String,
+    ) -> Self {
+        let empty_start = Board::new(height, width).to_notation(Cell::X);
+
+        GameRecord {
+            kind,
+            height,
+            width,
+            num_winner,
+            gravity,
+            start: Some(start).filter(|start| *start != empty_start),
+            moves: vec![],
+            result: GameOutcome::Ongoing,
+        }
+    }
+
+    /// Format the record in the PGN-like text format.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "[Game \"{:?}\"]", self.kind);
+        let _ = writeln!(text, "[Size \"{}x{}\"]", self.height, self.width);
+        let _ = writeln!(text, "[WinLength \"{}\"]", self.num_winner);
+        let _ = writeln!(text, "[Gravity \"{}\"]", self.gravity);
+        if let Some(start) = &self.start {
+            let _ = writeln!(text, "[FEN \"{}\"]", start);
+        }
+        let _ = writeln!(text, "[Result \"{}\"]", result_token(self.result));
+        text.push('\n');
+
+        for (num, pair) in self.moves.chunks(2).enumerate() {
+            let _ = write!(text, "{}.", num + 1);
+            for board_move in pair {
+                let _ = write!(text, " {},{}", board_move.coords.row, board_move.coords.col);
+            }
+            text.push(' ');
+        }
+        text += result_token(self.result);
+        text.push('\n');
+
+        text
+    }
+
+    /// Parse a record in the PGN-like text format.
+    pub fn from_text(text: &str) -> Result<Self, Error> {
+        let mut kind = None;
+        let mut size = None;
+        let mut num_winner = None;
+        let mut gravity = None;
+        let mut start = None;
+        let mut result = GameOutcome::Ongoing;
+        let mut coords = Vec::new();
+
+        for line in text.lines().map(str::trim) {
+            if let Some(tag) = line.strip_prefix('[') {
+                let (key, value) = match tag.strip_suffix(']').and_then(|tag| tag.split_once(' ')) {
+                    Some((key, value)) => (key, value.trim().trim_matches('"')),
+                    None => return Err(Error::InvalidRecord(format!("Malformed tag '{}'", line))),
+                };
+
+                match key {
+                    "Game" => {
+                        kind = Some(match value {
+                            "TicTacToe" => GameKind::TicTacToe,
+                            "FourInARow" => GameKind::FourInARow,
+                            "ConnectN" => GameKind::ConnectN,
+                            _ => {
+                                return Err(Error::InvalidRecord(format!(
+                                    "Unknown game '{}'",
+                                    value
+                                )))
+                            }
+                        })
+                    }
+                    "Size" => {
+                        size = value.split_once('x').and_then(|(height, width)| {
+                            Some((height.parse::<u32>().ok()?, width.parse::<u32>().ok()?))
+                        })
+                    }
+                    "WinLength" => num_winner = value.parse::<i32>().ok(),
+                    "Gravity" => gravity = value.parse::<bool>().ok(),
+                    "FEN" => start = Some(value.to_owned()),
+                    "Result" => result = parse_result(value)?,
+                    // Unknown tags like the date or names are skipped
+                    _ => (),
+                }
+                continue;
+            }
+
+            for token in line.split_whitespace() {
+                if token.ends_with('.') || parse_result(token).is_ok() {
+                    continue;
+                }
+
+                match token.split_once(',').and_then(|(row, col)| {
+                    Some((row.parse::<u32>().ok()?, col.parse::<u32>().ok()?))
+                }) {
+                    Some(move_coords) => coords.push(move_coords),
+                    None => {
+                        return Err(Error::InvalidRecord(format!("Malformed move '{}'", token)))
+                    }
+                }
+            }
+        }
+
+        let (kind, (height, width), num_winner) = match (kind, size, num_winner) {
+            (Some(kind), Some(size), Some(num_winner)) => (kind, size, num_winner),
+            _ => {
+                return Err(Error::InvalidRecord(
+                    "Record needs valid Game, Size and WinLength tags".to_owned(),
+                ))
+            }
+        };
+        let gravity = match (gravity, kind.fixed_rules()) {
+            (Some(gravity), _) => gravity,
+            (None, Some((_, _, _, gravity))) => gravity,
+            (None, None) => {
+                return Err(Error::InvalidRecord(
+                    "Record needs a valid Gravity tag".to_owned(),
+                ))
+            }
+        };
+
+        let mut record = GameRecord {
+            kind,
+            height,
+            width,
+            num_winner,
+            gravity,
+            start,
+            moves: vec![],
+            result,
+        };
+
+        let mut side = record.first_side()?;
+        for (row, col) in coords {
+            record.moves.push(BoardMove::new(row, col, side));
+            side = match side {
+                Cell::X => Cell::O,
+                _ => Cell::X,
+            };
+        }
+
+        record.validate()?;
+        Ok(record)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Serializable record")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let record: GameRecord =
+            serde_json::from_str(json).map_err(|err| Error::InvalidRecord(err.to_string()))?;
+        record.validate()?;
+        Ok(record)
+    }
+
+    /// Parse a record either in JSON or in the text format.
+    pub fn parse(record: &str) -> Result<Self, Error> {
+        match record.trim_start().starts_with('{') {
+            true => Self::from_json(record),
+            false => Self::from_text(record),
+        }
+    }
+
+    /// Side which did the first move of the record.
+    fn first_side(&self) -> Result<Cell, Error> {
+        match &self.start {
+            Some(start) => Ok(Board::from_notation(start)?.1),
+            None => Ok(Cell::X),
+        }
+    }
+
+    /// Check that the rules, the start position and the sides of the moves
+    /// fit together. The legality of the moves is checked on replay.
+    fn validate(&self) -> Result<(), Error> {
+        if let Some(rules) = self.kind.fixed_rules() {
+            if rules != (self.height, self.width, self.num_winner, self.gravity) {
+                return Err(Error::InvalidRecord(format!(
+                    "Rules of the record do not match {:?}",
+                    self.kind
+                )));
+            }
+        }
+
+        if let Some(start) = &self.start {
+            let (board, _) = Board::from_notation(start)?;
+            if (board.height(), board.width()) != (self.height, self.width) {
+                return Err(Error::InvalidRecord(
+                    "Start position does not match the board size".to_owned(),
+                ));
+            }
+        }
+
+        let mut side = self.first_side()?;
+        for board_move in self.moves.iter() {
+            if board_move.side != side {
+                return Err(Error::InvalidRecord(format!(
+                    "Move {:?} is not by {:?}",
+                    board_move, side
+                )));
+            }
+            side = match side {
+                Cell::X => Cell::O,
+                _ => Cell::X,
+            };
+        }
+
+        Ok(())
+    }
+}
+
+fn result_token(result: GameOutcome) -> &'static str {
+    match result {
+        GameOutcome::Win(Cell::X) => "1-0",
+        GameOutcome::Win(_) => "0-1",
+        GameOutcome::Draw => "1/2-1/2",
+        GameOutcome::Ongoing => "*",
+    }
+}
+
+fn parse_result(token: &str) -> Result<GameOutcome, Error> {
+    match token {
+        "1-0" => Ok(GameOutcome::Win(Cell::X)),
+        "0-1" => Ok(GameOutcome::Win(Cell::O)),
+        "1/2-1/2" => Ok(GameOutcome::Draw),
+        "*" => Ok(GameOutcome::Ongoing),
+        _ => Err(Error::InvalidRecord(format!("Unknown result '{}'", token))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{GameKind, GameRecord};
+    use crate::{
+        BoardMove, Cell, Error, FiarGameInterface, GameOutcome, IllegalMoveReason, T3GameInterface,
+    };
+
+    fn fiar_record() -> GameRecord {
+        let mut record = GameRecord::new(
+            GameKind::FourInARow,
+            6,
+            7,
+            4,
+            true,
+            "7/7/7/7/7/7 X".to_owned(),
+        );
+        for (row, col, side) in [
+            (5, 3, Cell::X),
+            (5, 2, Cell::O),
+            (4, 3, Cell::X),
+            (4, 2, Cell::O),
+            (3, 3, Cell::X),
+            (3, 2, Cell::O),
+            (2, 3, Cell::X),
+        ] {
+            record.moves.push(BoardMove::new(row, col, side));
+        }
+        record.result = GameOutcome::Win(Cell::X);
+        record
+    }
+
+    #[test]
+    fn test_record_text_and_json() {
+        let record = fiar_record();
+        assert_eq!(record.start, None);
+
+        let text = record.to_text();
+        assert_eq!(
+            text,
+            "[Game \"FourInARow\"]\n[Size \"6x7\"]\n[WinLength \"4\"]\n[Gravity \"true\"]\n\
+             [Result \"1-0\"]\n\n1. 5,3 5,2 2. 4,3 4,2 3. 3,3 3,2 4. 2,3 1-0\n"
+        );
+        assert_eq!(GameRecord::parse(&text).unwrap(), record);
+        assert_eq!(GameRecord::parse(&record.to_json()).unwrap(), record);
+
+        // Start positions define the side of the first move
+        let mut record =
+            GameRecord::new(GameKind::TicTacToe, 3, 3, 3, false, "3/1X1/3 O".to_owned());
+        record.moves.push(BoardMove::new(0, 0, Cell::O));
+        assert_eq!(GameRecord::from_text(&record.to_text()).unwrap(), record);
+
+        // Moves of the wrong side and rules which do not match the game
+        record.moves[0].side = Cell::X;
+        assert!(GameRecord::from_json(&record.to_json()).is_err());
+        assert!(
+            GameRecord::from_text("[Game \"TicTacToe\"]\n[Size \"4x4\"]\n[WinLength \"3\"]")
+                .is_err()
+        );
+        assert!(GameRecord::from_text("[Game \"Chess\"]").is_err());
+    }
+
+    #[test]
+    fn test_replay_record() {
+        let record = fiar_record();
+
+        let mut game_if = FiarGameInterface::new();
+        game_if.replay_record(&record).unwrap();
+        assert_eq!(game_if.outcome(), GameOutcome::Win(Cell::X));
+        assert_eq!(game_if.to_record(), record);
+
+        // Illegal moves and records of other games are rejected
+        let mut illegal = record.clone();
+        illegal.moves[2] = BoardMove::new(3, 3, Cell::X);
+        assert_eq!(
+            FiarGameInterface::new().replay_record(&illegal),
+            Err(Error::IllegalMove(
+                BoardMove::new(3, 3, Cell::X),
+                IllegalMoveReason::Floating
+            ))
+        );
+        assert!(T3GameInterface::new().replay_record(&record).is_err());
+
+        // A record of a game in progress continues from its last move
+        let mut t3_if = T3GameInterface::new();
+        t3_if.expand_one_level();
+        t3_if.track_move(BoardMove::new(1, 1, Cell::X)).unwrap();
+        t3_if.expand_one_level();
+        t3_if.track_move(BoardMove::new(0, 0, Cell::O)).unwrap();
+        let record = t3_if.to_record();
+        assert_eq!(record.result, GameOutcome::Ongoing);
+
+        let mut replayed = T3GameInterface::new();
+        replayed.load_record(&record.to_text()).unwrap();
+        assert_eq!(replayed.to_notation(), "O2/1X1/3 X");
+        assert_eq!(replayed.to_record(), record);
+    }
+}
@@ -0,0 +1,168 @@
+use crate::{Board, Cell, X_WIN_VALUE};
+use wasm_bindgen::prelude::*;
+
+/// Scores are clamped to this limit so that terminal wins always dominate.
+pub const HEURISTIC_LIMIT: i32 = X_WIN_VALUE / 2;
+
+/// Score of a window of `num_winner` cells which contains only pieces of one
+/// side, indexed by the number of pieces missing to complete the window.
+/// Complete windows are wins, which are scored by the game states. In
+/// four-in-a-row, a window missing a single piece is a three, i.e. a threat,
+/// one missing two pieces is a two and one missing three pieces holds a
+/// single piece. Even emptier windows of longer lines score 1.
+const WINDOW_SCORES: [i32; 4] = [0, 100, 10, 2];
+
+/// Score per piece for being close to the center of the board.
+pub(crate) const CENTER_SCORE: i32 = 3;
+
+/// Static evaluation of positions which are not final.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Heuristic {
+    /// Only final positions have a value, everything else looks like a draw.
+    #[default]
+    WinLoss,
+    /// Score open lines, threats and center control.
+    Lines,
+}
+
+impl Heuristic {
+    /// Estimate the value of `board` for a game with `num_winner` in a row.
+    ///
+    /// Positive values favor X and negative values favor O. The value is
+    /// always within `HEURISTIC_LIMIT`, so it has to be called only for
+    /// positions which are not final.
+    pub fn evaluate(&self, board: &Board, num_winner: i32) -> i32 {
+        match self {
+            Heuristic::WinLoss => 0,
+            Heuristic::Lines => {
+                let value = line_score(board, num_winner) + center_score(board);
+                value.clamp(-HEURISTIC_LIMIT, HEURISTIC_LIMIT)
+            }
+        }
+    }
+}
+
+/// Sum the scores of all windows of `num_winner` cells on all lines.
+///
+/// Windows with pieces of both sides can never be completed and do not count.
+fn line_score(board: &Board, num_winner: i32) -> i32 {
+    let window_len = num_winner as usize;
+    let mut score = 0;
+
+    for (pos, d_pos) in board.lines() {
+        // Slide the window along the line while counting the pieces in it
+        let (mut num_x, mut num_o) = (0, 0);
+        let (mut row, mut col) = (pos.row as i32, pos.col as i32);
+        let mut idx = 0;
+
+        while board.in_bounds(row as u32, col as u32) {
+            match board.cells()[board.get_index(row as u32, col as u32)] {
+                Cell::X => num_x += 1,
+                Cell::O => num_o += 1,
+                Cell::Empty => (),
+            }
+
+            if idx >= window_len {
+                let tail_row = row - d_pos.row * window_len as i32;
+                let tail_col = col - d_pos.col * window_len as i32;
+                match board.cells()[board.get_index(tail_row as u32, tail_col as u32)] {
+                    Cell::X => num_x -= 1,
+                    Cell::O => num_o -= 1,
+                    Cell::Empty => (),
+                }
+            }
+
+            if idx + 1 >= window_len {
+                match (num_x, num_o) {
+                    (0, 0) => (),
+                    (num_x, 0) => score += window_score(window_len - num_x),
+                    (0, num_o) => score -= window_score(window_len - num_o),
+                    _ => (),
+                }
+            }
+
+            row += d_pos.row;
+            col += d_pos.col;
+            idx += 1;
+        }
+    }
+
+    score
+}
+
+pub(crate) fn window_score(num_missing: usize) -> i32 {
+    WINDOW_SCORES.get(num_missing).copied().unwrap_or(1)
+}
+
+/// Reward pieces close to the center since they take part in more lines.
+fn center_score(board: &Board) -> i32 {
+    let (height, width) = (board.height() as i32, board.width() as i32);
+
+    board
+        .cells()
+        .iter()
+        .enumerate()
+        .map(|(idx, &cell)| {
+            let coords = board.get_coords(idx);
+            // Doubled distances keep the center of even-sized boards exact
+            let row_dist = (2 * coords.row as i32 - (height - 1)).abs();
+            let col_dist = (2 * coords.col as i32 - (width - 1)).abs();
+            let closeness = (height - 1 - row_dist) / 2 + (width - 1 - col_dist) / 2;
+
+            match cell {
+                Cell::X => CENTER_SCORE * closeness,
+                Cell::O => -CENTER_SCORE * closeness,
+                Cell::Empty => 0,
+            }
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Heuristic, HEURISTIC_LIMIT};
+    use crate::{Board, BoardMove, Cell, FiarGameState, GameState, X_WIN_VALUE};
+
+    #[test]
+    fn test_lines_heuristic() {
+        let empty = Board::new(6, 7);
+        assert_eq!(Heuristic::Lines.evaluate(&empty, 4), 0);
+
+        // A piece in the center column is worth more than one on the edge
+        let mut center = Board::new(6, 7);
+        center.set_cell(5, 3, Cell::X);
+        let mut edge = Board::new(6, 7);
+        edge.set_cell(5, 0, Cell::X);
+        assert!(Heuristic::Lines.evaluate(&center, 4) > Heuristic::Lines.evaluate(&edge, 4));
+
+        // Three in a row with open ends outweigh the opponent's scattered pieces
+        let mut three = Board::new(6, 7);
+        for col in 2..5 {
+            three.set_cell(5, col, Cell::O);
+        }
+        three.set_cell(4, 3, Cell::X);
+        three.set_cell(4, 2, Cell::X);
+        three.set_cell(3, 3, Cell::X);
+        let value = Heuristic::Lines.evaluate(&three, 4);
+        assert!(value < 0);
+        assert!(value > -HEURISTIC_LIMIT);
+
+        // Without heuristic, all non-final positions look like a draw
+        assert_eq!(Heuristic::WinLoss.evaluate(&three, 4), 0);
+    }
+
+    #[test]
+    fn test_win_dominates_heuristic() {
+        let mut board = Board::new(6, 7);
+        for col in 0..4 {
+            board.set_cell(5, col, Cell::X);
+        }
+        for col in 0..3 {
+            board.set_cell(4, col, Cell::O);
+        }
+
+        let state = FiarGameState::new(board, BoardMove::new(5, 3, Cell::X));
+        assert_eq!(state.heuristic_value(Heuristic::Lines), X_WIN_VALUE);
+    }
+}
@@ -1,29 +1,133 @@
+mod alpha_beta;
+pub use alpha_beta::{AlphaBetaSearch, DeepeningResult, SearchBudget};
+
+mod analysis;
+pub use analysis::{Analysis, MoveScore};
+
 mod board;
 pub use board::Board;
 
 mod common;
-pub use common::{BoardMove, Cell, Coords, DeltaCoords};
+pub use common::{BoardMove, Cell, Coords, DeltaCoords, GameOutcome, Symmetry, WinningLines};
+
+mod connect_n_game;
+pub use connect_n_game::ConnectNGameState;
+
+mod difficulty;
+pub use difficulty::{best_positions, Difficulty, DifficultyLevel, TieBreak};
+
+mod error;
+pub use error::{Error, IllegalMoveReason};
+
+mod fiar_bitboard;
+pub use fiar_bitboard::FiarBitboardState;
 
 mod fiar_game;
 pub use fiar_game::FiarGameState;
 
+mod game_record;
+pub use game_record::{GameKind, GameRecord};
+
+mod heuristic;
+pub use heuristic::{Heuristic, HEURISTIC_LIMIT};
+
+mod logging;
+pub use logging::{
+    log_level, set_log_level, set_logger, CollectingLogger, ConsoleLogger, LogEvent, LogLevel,
+    Logger, SilentLogger,
+};
+
 mod game_interface;
-pub use game_interface::{ExpandResult, FiarGameInterface, T3GameInterface};
+pub use game_interface::{
+    ConnectNGameInterface, ExpandResult, FiarGameInterface, FiarMctsInterface, SearchMode,
+    SearchResult, T3GameInterface, T3MctsInterface,
+};
+
+mod opening_book;
+pub use opening_book::{BookMove, OpeningBook};
+
+mod perft;
+pub use perft::perft;
 
 mod t3_game;
 pub use t3_game::T3GameState;
 
+mod transposition;
+pub use transposition::TranspositionTable;
+
 mod tree_evaluator;
 pub use tree_evaluator::TreeEvaluator;
 
+mod mcts;
+pub use mcts::MctsEvaluator;
+
+mod rng;
+pub use rng::Rng;
+
+mod utils;
+pub use utils::now_ms;
+
 pub const X_WIN_VALUE: i32 = 1000000;
 
 pub trait GameState {
     fn expand(&self) -> Vec<Self>
     where
         Self: Sized;
+    /// The states of `expand` one by one, which states with cheap children
+    /// can yield without allocating.
+    fn children(&self) -> impl Iterator<Item = Self>
+    where
+        Self: Sized,
+    {
+        self.expand().into_iter()
+    }
     fn position_value(&self) -> i32;
+    /// Value of the position for a search which stops here.
+    ///
+    /// Final positions keep their position value. Other positions are
+    /// estimated with `heuristic`.
+    fn heuristic_value(&self, _heuristic: Heuristic) -> i32 {
+        self.position_value()
+    }
     fn side(&self) -> Cell;
+    /// Check whether `board_move` can be played in this position.
+    fn validate_move(&self, board_move: &BoardMove) -> Result<(), IllegalMoveReason> {
+        if self.outcome().is_over() {
+            return Err(IllegalMoveReason::GameOver);
+        }
+        if board_move.side == self.side() || board_move.side == Cell::Empty {
+            return Err(IllegalMoveReason::WrongSide);
+        }
+        self.validate_coords(&board_move.coords)
+    }
+    /// Check whether the side to move may place a piece on `coords`.
+    fn validate_coords(&self, coords: &Coords) -> Result<(), IllegalMoveReason>;
+    /// Number of rows and columns of the board.
+    fn board_size(&self) -> (u32, u32);
+    /// Move which led to this state.
+    fn last_move(&self) -> BoardMove;
+    /// Whether the game is won, drawn or still ongoing.
+    fn outcome(&self) -> GameOutcome;
+    /// Hash identifying the position, equal for the same position reached by
+    /// different move orders.
+    fn zobrist_hash(&self) -> u64;
+    /// Symmetries of the board which keep the rules of the game.
+    fn symmetries(&self) -> Vec<Symmetry> {
+        vec![Symmetry::Identity]
+    }
+    /// Equivalent state with the board and the last move moved by `symmetry`.
+    fn transformed(&self, symmetry: Symmetry) -> Self
+    where
+        Self: Sized;
+    /// Hash which is equal for positions that are symmetric to each other.
+    fn canonical_hash(&self) -> u64
+    where
+        Self: Sized,
+    {
+        self.symmetries()
+            .iter()
+            .map(|&symmetry| self.transformed(symmetry).zobrist_hash())
+            .min()
+            .unwrap_or_else(|| self.zobrist_hash())
+    }
 }
-
-pub type Error = Box<dyn std::error::Error>;
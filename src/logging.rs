@@ -0,0 +1,281 @@
+use crate::BoardMove;
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+
+/// Severity of a log event, events above the selected level are dropped.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+/// Event of the engine with its data kept structured so that collectors can
+/// inspect it without parsing messages.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LogEvent {
+    /// New positions were added to the tree.
+    NodesGenerated {
+        nodes: usize,
+    },
+    /// The tree was expanded by one level.
+    LevelExpanded {
+        depth: usize,
+        transposition_hits: usize,
+        transposition_misses: usize,
+    },
+    /// The expansion reached its target depth.
+    ExpansionDone {
+        nodes: usize,
+    },
+    /// A search for the best move finished.
+    SearchFinished {
+        depth: usize,
+        value: i32,
+        nodes: usize,
+        millis: f64,
+    },
+    /// MCTS iterations were run for one time slice.
+    MctsIterations {
+        iterations: usize,
+        visits: u32,
+        nodes: usize,
+    },
+    /// States which cannot be reached anymore were dropped.
+    TreePruned {
+        nodes_before: usize,
+        nodes_after: usize,
+    },
+    MoveTracked(BoardMove),
+    MovePlayed(BoardMove),
+    /// A move was taken from the opening book instead of searching.
+    BookMovePlayed(BoardMove),
+    MoveUndone(BoardMove),
+    Message(String),
+}
+
+impl fmt::Display for LogEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogEvent::NodesGenerated { nodes } => write!(f, "Generated {} new nodes", nodes),
+            LogEvent::LevelExpanded {
+                depth,
+                transposition_hits,
+                transposition_misses,
+            } => write!(
+                f,
+                "Expanded level {} (transpositions: {} hits, {} misses)",
+                depth, transposition_hits, transposition_misses
+            ),
+            LogEvent::ExpansionDone { nodes } => write!(f, "Expansion done with {} nodes", nodes),
+            LogEvent::SearchFinished {
+                depth,
+                value,
+                nodes,
+                millis,
+            } => write!(
+                f,
+                "Search reached depth {} with value {} in {:.0}ms ({} nodes)",
+                depth, value, millis, nodes
+            ),
+            LogEvent::MctsIterations {
+                iterations,
+                visits,
+                nodes,
+            } => write!(
+                f,
+                "Ran {} MCTS iterations, {} visits and {} nodes in total",
+                iterations, visits, nodes
+            ),
+            LogEvent::TreePruned {
+                nodes_before,
+                nodes_after,
+            } => write!(
+                f,
+                "Pruned tree from {} to {} nodes",
+                nodes_before, nodes_after
+            ),
+            LogEvent::MoveTracked(board_move) => write!(f, "Tracked move {:?}", board_move),
+            LogEvent::MovePlayed(board_move) => write!(f, "Playing best move {:?}", board_move),
+            LogEvent::BookMovePlayed(board_move) => {
+                write!(f, "Playing book move {:?}", board_move)
+            }
+            LogEvent::MoveUndone(board_move) => write!(f, "Undo move {:?}", board_move),
+            LogEvent::Message(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+/// Receiver of the log events which pass the selected level.
+pub trait Logger {
+    fn log(&self, level: LogLevel, event: &LogEvent);
+}
+
+/// Logs to the browser console in Wasm and to stderr natively.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ConsoleLogger;
+
+impl Logger for ConsoleLogger {
+    fn log(&self, level: LogLevel, event: &LogEvent) {
+        #[cfg(target_arch = "wasm32")]
+        {
+            let message = event.to_string().into();
+            match level {
+                LogLevel::Error => web_sys::console::error_1(&message),
+                LogLevel::Warn => web_sys::console::warn_1(&message),
+                LogLevel::Debug => web_sys::console::debug_1(&message),
+                _ => web_sys::console::log_1(&message),
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        eprintln!("[{:?}] {}", level, event);
+    }
+}
+
+/// Drops all events.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SilentLogger;
+
+impl Logger for SilentLogger {
+    fn log(&self, _level: LogLevel, _event: &LogEvent) {}
+}
+
+/// Keeps all events, e.g. to check them in tests or to print statistics.
+///
+/// Clones share the collected events, so a clone can be installed with
+/// `set_logger` while the original is used to read the events.
+#[derive(Clone, Debug, Default)]
+pub struct CollectingLogger {
+    events: Rc<RefCell<Vec<(LogLevel, LogEvent)>>>,
+}
+
+impl CollectingLogger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn events(&self) -> Vec<(LogLevel, LogEvent)> {
+        self.events.borrow().clone()
+    }
+
+    pub fn clear(&self) {
+        self.events.borrow_mut().clear();
+    }
+}
+
+impl Logger for CollectingLogger {
+    fn log(&self, level: LogLevel, event: &LogEvent) {
+        self.events.borrow_mut().push((level, event.clone()));
+    }
+}
+
+thread_local! {
+    // Wasm runs single-threaded and every worker has its own instance, so
+    // the logger is kept per thread. Unit tests are silent by default.
+    static LOGGER: RefCell<Box<dyn Logger>> = RefCell::new(default_logger());
+    static LEVEL: Cell<LogLevel> = const { Cell::new(LogLevel::Info) };
+}
+
+#[cfg(not(test))]
+fn default_logger() -> Box<dyn Logger> {
+    Box::new(ConsoleLogger)
+}
+
+#[cfg(test)]
+fn default_logger() -> Box<dyn Logger> {
+    Box::new(SilentLogger)
+}
+
+/// Install `logger` for all following events of this thread.
+pub fn set_logger(logger: Box<dyn Logger>) {
+    LOGGER.with(|cur_logger| *cur_logger.borrow_mut() = logger);
+}
+
+/// Only log events up to `level`, e.g. `LogLevel::Warn` skips the progress
+/// of the search.
+#[wasm_bindgen]
+pub fn set_log_level(level: LogLevel) {
+    LEVEL.with(|cur_level| cur_level.set(level));
+}
+
+pub fn log_level() -> LogLevel {
+    LEVEL.with(|cur_level| cur_level.get())
+}
+
+/// Pass `event` to the logger if `level` is enabled.
+pub fn log_event(level: LogLevel, event: LogEvent) {
+    if level == LogLevel::Off || level > log_level() {
+        return;
+    }
+
+    LOGGER.with(|logger| logger.borrow().log(level, &event));
+}
+
+pub fn error(event: LogEvent) {
+    log_event(LogLevel::Error, event);
+}
+
+pub fn warn(event: LogEvent) {
+    log_event(LogLevel::Warn, event);
+}
+
+pub fn info(event: LogEvent) {
+    log_event(LogLevel::Info, event);
+}
+
+pub fn debug(event: LogEvent) {
+    log_event(LogLevel::Debug, event);
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        debug, info, log_level, set_log_level, set_logger, warn, CollectingLogger, LogEvent,
+        LogLevel,
+    };
+    use crate::{T3GameState, TreeEvaluator};
+
+    #[test]
+    fn test_collect_events_by_level() {
+        let collector = CollectingLogger::new();
+        set_logger(Box::new(collector.clone()));
+        assert_eq!(log_level(), LogLevel::Info);
+
+        debug(LogEvent::Message("Hidden".to_owned()));
+        info(LogEvent::NodesGenerated { nodes: 3 });
+        warn(LogEvent::Message("Shown".to_owned()));
+        assert_eq!(
+            collector.events(),
+            vec![
+                (LogLevel::Info, LogEvent::NodesGenerated { nodes: 3 }),
+                (LogLevel::Warn, LogEvent::Message("Shown".to_owned())),
+            ]
+        );
+
+        // Events of the engine are structured
+        collector.clear();
+        let mut tree_eval = TreeEvaluator::new(T3GameState::default());
+        let children_idx = tree_eval.expand_and_get_children_idx(&[0]);
+        tree_eval.expand_and_get_children_idx(&children_idx);
+        assert_eq!(
+            collector.events(),
+            vec![
+                (LogLevel::Info, LogEvent::NodesGenerated { nodes: 9 }),
+                (LogLevel::Info, LogEvent::NodesGenerated { nodes: 72 }),
+            ]
+        );
+
+        collector.clear();
+        set_log_level(LogLevel::Warn);
+        info(LogEvent::NodesGenerated { nodes: 1 });
+        set_log_level(LogLevel::Off);
+        warn(LogEvent::Message("Hidden".to_owned()));
+        assert!(collector.events().is_empty());
+    }
+}
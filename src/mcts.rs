@@ -0,0 +1,305 @@
+use crate::{now_ms, GameOutcome, GameState, Rng};
+
+/// Exploration constant of the UCT formula.
+const EXPLORATION: f64 = std::f64::consts::SQRT_2;
+
+/// Monte Carlo Tree Search with the UCT selection policy.
+///
+/// Like the `TreeEvaluator`, the tree is stored index-based in parallel
+/// vectors. Instead of expanding level by level, every iteration descends to
+/// the most promising leaf, expands it, plays a random game to the end and
+/// propagates the result back up. Iterations can be run in time-bounded
+/// slices, so the answer keeps improving until the best move is requested.
+pub struct MctsEvaluator<T> {
+    parent: Vec<usize>,
+    children: Vec<Vec<usize>>,
+    game_states: Vec<T>,
+    visits: Vec<u32>,
+    /// Sum of rewards for the side which did the move leading to the node
+    rewards: Vec<f64>,
+    rng: Rng,
+}
+
+impl<T> MctsEvaluator<T>
+where
+    T: GameState + Default,
+{
+    pub fn new(init_state: T) -> MctsEvaluator<T> {
+        MctsEvaluator {
+            parent: vec![0],
+            children: vec![vec![]],
+            game_states: vec![init_state],
+            visits: vec![0],
+            rewards: vec![0.0],
+            rng: Rng::default(),
+        }
+    }
+
+    pub fn new_with_default() -> MctsEvaluator<T> {
+        let init_state = T::default();
+        Self::new(init_state)
+    }
+
+    /// Seed the random number generator used for the random playouts.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+
+    /// Run `num_iterations` iterations below `root_idx`.
+    pub fn run_iterations(&mut self, root_idx: usize, num_iterations: usize) {
+        for _ in 0..num_iterations {
+            self.iterate(root_idx);
+        }
+    }
+
+    /// Run iterations below `root_idx` until `budget_ms` have passed.
+    ///
+    /// Returns the number of iterations which were run.
+    pub fn run_for(&mut self, root_idx: usize, budget_ms: f64) -> usize {
+        let stop_time = now_ms() + budget_ms;
+        let mut num_iterations = 0;
+
+        while now_ms() < stop_time {
+            // Check the time only every few iterations since it is
+            // comparatively expensive in Wasm.
+            self.run_iterations(root_idx, 16);
+            num_iterations += 16;
+        }
+
+        num_iterations
+    }
+
+    /// Expand the state at `idx` if it has no children yet.
+    pub fn expand_state(&mut self, idx: usize) {
+        if !self.children[idx].is_empty() || self.game_states[idx].outcome().is_over() {
+            return;
+        }
+
+        let mut child_states = self.game_states[idx].expand();
+        let first_child_idx = self.parent.len();
+        let num_children = child_states.len();
+
+        self.children[idx] = (first_child_idx..first_child_idx + num_children).collect();
+        self.parent.append(&mut vec![idx; num_children]);
+        self.children.append(&mut vec![vec![]; num_children]);
+        self.game_states.append(&mut child_states);
+        self.visits.append(&mut vec![0; num_children]);
+        self.rewards.append(&mut vec![0.0; num_children]);
+    }
+
+    /// Child of `idx` with the most visits, which is the most robust choice.
+    ///
+    /// Among equally often visited children, the first one is returned.
+    pub fn best_child(&self, idx: usize) -> Option<usize> {
+        self.children
+            .get(idx)?
+            .iter()
+            .fold(None, |best: Option<usize>, &child_idx| match best {
+                Some(best_idx) if self.visits[best_idx] >= self.visits[child_idx] => best,
+                _ => Some(child_idx),
+            })
+    }
+
+    /// Average reward of the node for the side which moved into it.
+    pub fn win_rate(&self, idx: usize) -> f64 {
+        match self.visits[idx] {
+            0 => 0.0,
+            visits => self.rewards[idx] / visits as f64,
+        }
+    }
+
+    pub fn num_nodes(&self) -> usize {
+        self.game_states.len()
+    }
+
+    pub fn game_states(&self) -> &Vec<T> {
+        &self.game_states
+    }
+
+    pub fn parent(&self) -> &Vec<usize> {
+        &self.parent
+    }
+
+    pub fn children(&self) -> &Vec<Vec<usize>> {
+        &self.children
+    }
+
+    pub fn visits(&self) -> &Vec<u32> {
+        &self.visits
+    }
+
+    fn iterate(&mut self, root_idx: usize) {
+        // The root is expanded right away, so a single iteration already
+        // visits a move
+        self.expand_state(root_idx);
+
+        // Selection - descend to a leaf
+        let mut idx = root_idx;
+        while !self.children[idx].is_empty() {
+            idx = self.select_child(idx);
+        }
+
+        // Expansion - leafs are expanded on their second visit so that we do
+        // not store children of nodes which are played out only once.
+        if self.visits[idx] > 0 {
+            self.expand_state(idx);
+            if let Some(&first_child_idx) = self.children[idx].first() {
+                idx = first_child_idx;
+            }
+        }
+
+        // Simulation and backpropagation
+        let outcome = self.rollout(idx);
+        self.backpropagate(idx, root_idx, outcome);
+    }
+
+    /// Select the child with the highest upper confidence bound.
+    ///
+    /// Children which were never visited are selected first.
+    fn select_child(&self, idx: usize) -> usize {
+        let ln_visits = (self.visits[idx].max(1) as f64).ln();
+
+        let (best_idx, _) = self.children[idx].iter().fold(
+            (idx, f64::NEG_INFINITY),
+            |(best_idx, best_bound), &child_idx| {
+                let bound = match self.visits[child_idx] {
+                    0 => f64::INFINITY,
+                    visits => {
+                        self.rewards[child_idx] / visits as f64
+                            + EXPLORATION * (ln_visits / visits as f64).sqrt()
+                    }
+                };
+
+                match bound > best_bound {
+                    true => (child_idx, bound),
+                    false => (best_idx, best_bound),
+                }
+            },
+        );
+
+        best_idx
+    }
+
+    /// Play random moves from the state at `idx` until the game is over.
+    fn rollout(&mut self, idx: usize) -> GameOutcome {
+        let outcome = self.game_states[idx].outcome();
+        if outcome.is_over() {
+            return outcome;
+        }
+
+        let mut child_states = self.game_states[idx].expand();
+        while !child_states.is_empty() {
+            let state = child_states.swap_remove(self.rng.gen_index(child_states.len()));
+            let outcome = state.outcome();
+            if outcome.is_over() {
+                return outcome;
+            }
+            child_states = state.expand();
+        }
+
+        // A state without moves which is not over should not exist
+        GameOutcome::Draw
+    }
+
+    fn backpropagate(&mut self, leaf_idx: usize, root_idx: usize, outcome: GameOutcome) {
+        let mut idx = leaf_idx;
+        loop {
+            self.visits[idx] += 1;
+            self.rewards[idx] += match outcome {
+                GameOutcome::Win(side) if side == self.game_states[idx].side() => 1.0,
+                GameOutcome::Draw => 0.5,
+                _ => 0.0,
+            };
+
+            if idx == root_idx {
+                break;
+            }
+            idx = self.parent[idx];
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        Board, BoardMove, Cell, FiarGameState, MctsEvaluator, T3GameState, T3MctsInterface,
+    };
+
+    #[test]
+    fn test_mcts_finds_winning_move() {
+        let mut b1 = Board::new(3, 3);
+        // X X
+        // O O
+        // X O
+        let _ = b1.set_state(vec![
+            Cell::X,
+            Cell::Empty,
+            Cell::X,
+            Cell::O,
+            Cell::Empty,
+            Cell::O,
+            Cell::X,
+            Cell::Empty,
+            Cell::O,
+        ]);
+
+        let mut mcts = MctsEvaluator::new(T3GameState::new(b1, BoardMove::new(2, 2, Cell::O)));
+        mcts.run_iterations(0, 200);
+
+        let best_idx = mcts.best_child(0).unwrap();
+        assert_eq!(
+            mcts.game_states()[best_idx].last_move(),
+            BoardMove::new(0, 1, Cell::X)
+        );
+        assert_eq!(mcts.win_rate(best_idx), 1.0);
+    }
+
+    #[test]
+    fn test_mcts_blocks_threat() {
+        let mut board = Board::new(6, 7);
+        for col in 0..3 {
+            board.set_cell(5, col, Cell::X);
+        }
+        board.set_cell(4, 0, Cell::O);
+        board.set_cell(4, 1, Cell::O);
+
+        // O has to block the fourth X in the bottom row
+        let mut mcts = MctsEvaluator::new(FiarGameState::new(board, BoardMove::new(5, 2, Cell::X)));
+        mcts.run_iterations(0, 2000);
+
+        let best_idx = mcts.best_child(0).unwrap();
+        assert_eq!(
+            mcts.game_states()[best_idx].last_move(),
+            BoardMove::new(5, 3, Cell::O)
+        );
+    }
+
+    #[test]
+    fn test_mcts_deterministic_with_seed() {
+        let mut first = MctsEvaluator::new(FiarGameState::default());
+        first.set_seed(7);
+        first.run_iterations(0, 300);
+
+        let mut second = MctsEvaluator::new(FiarGameState::default());
+        second.set_seed(7);
+        second.run_iterations(0, 300);
+
+        assert_eq!(first.num_nodes(), second.num_nodes());
+        assert_eq!(first.visits(), second.visits());
+        assert_eq!(first.visits()[0], 300);
+    }
+
+    #[test]
+    fn test_mcts_smallest_budgets() {
+        let mut mcts = MctsEvaluator::new(T3GameState::default());
+        mcts.run_iterations(0, 1);
+        assert_eq!(mcts.children()[0].len(), 9);
+        assert!(mcts.best_child(0).is_some());
+
+        // Even without a single iteration, the interfaces play a move
+        let mut mcts_if = T3MctsInterface::new();
+        assert!(mcts_if.get_best_move_with_nodes(1).is_ok());
+        assert!(mcts_if.get_best_move_within(0.0).is_ok());
+        assert!(T3MctsInterface::new().get_best_move_with_nodes(0).is_ok());
+    }
+}
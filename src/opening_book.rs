@@ -0,0 +1,441 @@
+use crate::{AlphaBetaSearch, Board, BoardMove, Cell, Coords, Error, GameState, Heuristic, Rng};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+/// Book of Four-in-a-row which is compiled into the binary, see the
+/// `fiar_book` tool to generate it.
+const FIAR_BOOK: &str = include_str!("../books/fiar.book");
+
+/// Move recommended by an `OpeningBook`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BookMove {
+    pub coords: Coords,
+    /// Relative weight among the moves of a position, the best has the highest
+    pub weight: u32,
+}
+
+/// Recommended moves for positions early in the game.
+///
+/// Positions are stored in the notation of `Board::from_notation`. The text
+/// format has one position per line, followed by its moves as
+/// `row,col:weight`:
+///
+/// ```text
+/// # Comment
+/// 7/7/7/7/7/7 X 5,3:6 5,2:2
+/// ```
+///
+/// Only one of several symmetric positions has to be stored, lookups try all
+/// symmetries of the game.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OpeningBook {
+    entries: HashMap<String, Vec<BookMove>>,
+}
+
+impl OpeningBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Book for Four-in-a-row which is embedded in the binary.
+    pub fn fiar() -> Self {
+        Self::parse(FIAR_BOOK).expect("Valid embedded book")
+    }
+
+    /// Parse a book in the text format.
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let mut book = Self::new();
+
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut tokens = line.split_whitespace();
+            let notation = match (tokens.next(), tokens.next()) {
+                (Some(board), Some(side)) => format!("{} {}", board, side),
+                _ => {
+                    return Err(Error::InvalidBook(format!(
+                        "Malformed book line '{}'",
+                        line
+                    )))
+                }
+            };
+            let (board, _) = Board::from_notation(&notation)?;
+
+            let mut moves = Vec::new();
+            for token in tokens {
+                let book_move = token.split_once(':').and_then(|(coords, weight)| {
+                    let (row, col) = coords.split_once(',')?;
+                    Some(BookMove {
+                        coords: Coords {
+                            row: row.parse().ok()?,
+                            col: col.parse().ok()?,
+                        },
+                        weight: weight.parse().ok()?,
+                    })
+                });
+
+                match book_move {
+                    Some(book_move)
+                        if board
+                            .get_cell(book_move.coords.row, book_move.coords.col)
+                            .ok()
+                            == Some(Cell::Empty) =>
+                    {
+                        moves.push(book_move)
+                    }
+                    _ => {
+                        return Err(Error::InvalidBook(format!(
+                            "Invalid book move '{}' in '{}'",
+                            token, line
+                        )))
+                    }
+                }
+            }
+
+            if moves.is_empty() {
+                return Err(Error::InvalidBook(format!(
+                    "Book line '{}' has no moves",
+                    line
+                )));
+            }
+            book.entries.insert(notation, moves);
+        }
+
+        Ok(book)
+    }
+
+    /// Format the book in the text format with sorted positions.
+    pub fn to_text(&self) -> String {
+        let mut notations: Vec<&String> = self.entries.keys().collect();
+        notations.sort();
+
+        let mut text = String::new();
+        for notation in notations {
+            text += notation;
+            for book_move in self.entries[notation].iter() {
+                let _ = write!(
+                    text,
+                    " {},{}:{}",
+                    book_move.coords.row, book_move.coords.col, book_move.weight
+                );
+            }
+            text.push('\n');
+        }
+
+        text
+    }
+
+    pub fn insert(&mut self, notation: String, moves: Vec<BookMove>) {
+        self.entries.insert(notation, moves);
+    }
+
+    /// Moves stored for the position in `notation`.
+    pub fn moves(&self, notation: &str) -> Option<&[BookMove]> {
+        self.entries.get(notation).map(Vec::as_slice)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Book move for the side to move after `state`, also if only a
+    /// symmetric position is stored.
+    ///
+    /// Without `rng`, the move with the highest weight is picked. Otherwise,
+    /// moves are picked randomly in proportion to their weight.
+    pub fn find_move<T: GameState>(
+        &self,
+        state: &T,
+        notation: impl Fn(&T) -> String,
+        rng: Option<&mut Rng>,
+    ) -> Option<BoardMove> {
+        let (symmetry, twin, moves) = state.symmetries().into_iter().find_map(|symmetry| {
+            let twin = state.transformed(symmetry);
+            let moves = self.moves(&notation(&twin))?;
+            Some((symmetry, twin, moves))
+        })?;
+
+        let coords = match rng {
+            Some(rng) => {
+                let total: u32 = moves.iter().map(|book_move| book_move.weight).sum();
+                let mut pick = (rng.next_f64() * total as f64) as u32;
+                moves
+                    .iter()
+                    .find(|book_move| match pick < book_move.weight {
+                        true => true,
+                        false => {
+                            pick -= book_move.weight;
+                            false
+                        }
+                    })
+                    .or(moves.last())?
+                    .coords
+            }
+            // The first of several moves with the highest weight
+            None => {
+                moves
+                    .iter()
+                    .rev()
+                    .max_by_key(|book_move| book_move.weight)?
+                    .coords
+            }
+        };
+
+        // Play the move on the stored twin and map it back to the board
+        twin.expand()
+            .into_iter()
+            .find(|child| child.last_move().coords == coords)
+            .map(|child| child.transformed(symmetry.inverse()).last_move())
+    }
+
+    /// Generate a book for the positions up to `plies` moves after `root`.
+    ///
+    /// Every position is searched `depth` plies deep and the moves within
+    /// `margin` of the best value are recommended. The best move gets a
+    /// weight of `margin + 1`, which decreases with the distance to the best
+    /// value. To cover the games of an engine playing either side, only the
+    /// recommended moves are followed for one side while all moves are
+    /// followed for the other one. Symmetric positions are stored once.
+    pub fn generate<T: GameState>(
+        root: &T,
+        notation: impl Fn(&T) -> String,
+        plies: usize,
+        depth: usize,
+        heuristic: Heuristic,
+        margin: u32,
+    ) -> Self {
+        let mut book = Self::new();
+        let mut visited: HashSet<(u64, Cell, usize)> = HashSet::new();
+        for book_side in [Cell::X, Cell::O] {
+            book.extend(
+                root,
+                &notation,
+                plies,
+                depth,
+                heuristic,
+                margin,
+                book_side,
+                &mut visited,
+            );
+        }
+
+        book
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn extend<T: GameState>(
+        &mut self,
+        state: &T,
+        notation: &impl Fn(&T) -> String,
+        plies: usize,
+        depth: usize,
+        heuristic: Heuristic,
+        margin: u32,
+        book_side: Cell,
+        visited: &mut HashSet<(u64, Cell, usize)>,
+    ) {
+        if plies == 0
+            || state.outcome().is_over()
+            || !visited.insert((state.canonical_hash(), book_side, plies))
+        {
+            return;
+        }
+
+        let children = state.expand();
+        let side_to_move = children.first().map(|child| child.side());
+        if side_to_move != Some(book_side) {
+            // All replies of the opponent
+            for child in children.iter() {
+                self.extend(
+                    child,
+                    notation,
+                    plies - 1,
+                    depth,
+                    heuristic,
+                    margin,
+                    book_side,
+                    visited,
+                );
+            }
+            return;
+        }
+
+        if self.find_move(state, notation, None).is_none() {
+            let moves = recommend(&children, depth, heuristic, margin);
+            self.insert(notation(state), moves);
+        }
+
+        for child in self.recommended_children(state, &children, notation) {
+            self.extend(
+                child,
+                notation,
+                plies - 1,
+                depth,
+                heuristic,
+                margin,
+                book_side,
+                visited,
+            );
+        }
+    }
+
+    /// Children of `state` which the book recommends, also if only a
+    /// symmetric position is stored.
+    fn recommended_children<'c, T: GameState>(
+        &self,
+        state: &T,
+        children: &'c [T],
+        notation: &impl Fn(&T) -> String,
+    ) -> Vec<&'c T> {
+        state
+            .symmetries()
+            .into_iter()
+            .find_map(|symmetry| {
+                let twin = state.transformed(symmetry);
+                let moves = self.moves(&notation(&twin))?;
+                Some(
+                    children
+                        .iter()
+                        .filter(|child| {
+                            let coords = child.transformed(symmetry).last_move().coords;
+                            moves.iter().any(|book_move| book_move.coords == coords)
+                        })
+                        .collect(),
+                )
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Moves to `children` within `margin` of the best value of a search with
+/// `depth` plies.
+fn recommend<T: GameState>(
+    children: &[T],
+    depth: usize,
+    heuristic: Heuristic,
+    margin: u32,
+) -> Vec<BookMove> {
+    let child_refs: Vec<&T> = children.iter().collect();
+    let mut search = AlphaBetaSearch::new(heuristic);
+    let values = search.child_values(&child_refs, depth);
+
+    // Values from the perspective of the side to move
+    let sign = match children.first().map(|child| child.side()) {
+        Some(Cell::O) => -1,
+        _ => 1,
+    };
+    let best = values.iter().map(|value| sign * value).max().unwrap_or(0);
+
+    let mut moves: Vec<BookMove> = children
+        .iter()
+        .zip(values)
+        .filter_map(|(child, value)| {
+            let distance = (best - sign * value) as u32;
+            (distance <= margin).then(|| BookMove {
+                coords: child.last_move().coords,
+                weight: margin + 1 - distance,
+            })
+        })
+        .collect();
+    moves.sort_by_key(|book_move| std::cmp::Reverse(book_move.weight));
+
+    moves
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BookMove, OpeningBook};
+    use crate::{
+        BoardMove, Cell, Coords, FiarGameInterface, FiarGameState, Heuristic, Rng, T3GameState,
+    };
+
+    #[test]
+    fn test_book_text() {
+        let text = "# Center first\n7/7/7/7/7/7 X 5,3:3 5,2:1\n";
+        let book = OpeningBook::parse(text).unwrap();
+        assert_eq!(book.len(), 1);
+        assert_eq!(
+            book.moves("7/7/7/7/7/7 X").unwrap()[0],
+            BookMove {
+                coords: Coords { row: 5, col: 3 },
+                weight: 3,
+            }
+        );
+        assert_eq!(OpeningBook::parse(&book.to_text()).unwrap(), book);
+
+        // Occupied cells, missing moves and malformed positions
+        assert!(OpeningBook::parse("7/7/7/7/7/3X3 O 5,3:1").is_err());
+        assert!(OpeningBook::parse("7/7/7/7/7/7 X").is_err());
+        assert!(OpeningBook::parse("7/7/7 X 0,0:1:2").is_err());
+
+        // The embedded book starts in the center
+        let book = OpeningBook::fiar();
+        assert!(book.len() > 10);
+        assert_eq!(
+            book.find_move(&FiarGameState::default(), FiarGameState::to_notation, None),
+            Some(BoardMove::new(5, 3, Cell::X))
+        );
+    }
+
+    #[test]
+    fn test_book_symmetries() {
+        // X played on the left, only the mirrored position is stored
+        let book = OpeningBook::parse("7/7/7/7/7/4X2 O 4,4:2 5,5:1").unwrap();
+        let state = FiarGameState::from_notation("7/7/7/7/7/2X4 O").unwrap();
+        assert_eq!(
+            book.find_move(&state, FiarGameState::to_notation, None),
+            Some(BoardMove::new(4, 2, Cell::O))
+        );
+
+        // Weighted random picks only yield book moves
+        let mut rng = Rng::new(18);
+        let picks: Vec<BoardMove> = (0..50)
+            .filter_map(|_| book.find_move(&state, FiarGameState::to_notation, Some(&mut rng)))
+            .collect();
+        assert_eq!(picks.len(), 50);
+        assert!(picks.contains(&BoardMove::new(4, 2, Cell::O)));
+        assert!(picks.contains(&BoardMove::new(5, 1, Cell::O)));
+        assert!(picks
+            .iter()
+            .all(|pick| pick.coords.col == 1 || pick.coords.col == 2));
+    }
+
+    #[test]
+    fn test_generate_book() {
+        // With the full game searched, only the center and the corners are
+        // not losing for O after X took an edge
+        let book = OpeningBook::generate(
+            &T3GameState::default(),
+            T3GameState::to_notation,
+            2,
+            9,
+            Heuristic::WinLoss,
+            0,
+        );
+        let moves = book.moves("3/3/3 X").unwrap();
+        assert_eq!(moves.len(), 9);
+        assert!(moves.iter().all(|book_move| book_move.weight == 1));
+
+        // After X took an edge, O must not take the far corners or the
+        // neighboring edges
+        let state = T3GameState::from_notation("1X1/3/3 O").unwrap();
+        let mut rng = Rng::new(18);
+        let mut picks: Vec<(u32, u32)> = (0..100)
+            .filter_map(|_| book.find_move(&state, T3GameState::to_notation, Some(&mut rng)))
+            .map(|pick| (pick.coords.row, pick.coords.col))
+            .collect();
+        picks.sort_unstable();
+        picks.dedup();
+        assert_eq!(picks, vec![(0, 0), (0, 2), (1, 1), (2, 1)]);
+
+        // Interfaces play from the book before searching
+        let mut game_if = FiarGameInterface::new();
+        assert_eq!(game_if.get_best_move(), Ok(BoardMove::new(5, 3, Cell::X)));
+    }
+}
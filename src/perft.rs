@@ -0,0 +1,52 @@
+use crate::GameState;
+
+/// Count the leaf nodes of the game tree below `state` down to `depth` plies.
+///
+/// Leaves are the states at `depth` and the states where the game ended
+/// earlier, e.g. all complete games with a depth of the board size. Known
+/// counts for the empty boards verify that `expand` generates exactly the
+/// legal moves.
+pub fn perft<T: GameState>(state: &T, depth: usize) -> usize {
+    if depth == 0 || state.outcome().is_over() {
+        return 1;
+    }
+
+    state.children().map(|child| perft(&child, depth - 1)).sum()
+}
+
+#[cfg(test)]
+mod test {
+    use super::perft;
+    use crate::{ConnectNGameState, FiarGameState, T3GameState};
+
+    #[test]
+    fn test_perft_t3() {
+        let state = T3GameState::default();
+        let counts: Vec<usize> = (1..=5).map(|depth| perft(&state, depth)).collect();
+        assert_eq!(counts, vec![9, 72, 504, 3024, 15120]);
+
+        // 1440 games end with the fifth move
+        assert_eq!(perft(&state, 6), 54720 + 1440);
+        // Number of complete games
+        assert_eq!(perft(&state, 9), 255168);
+    }
+
+    #[test]
+    fn test_perft_fiar() {
+        let state = FiarGameState::default();
+        let counts: Vec<usize> = (1..=6).map(|depth| perft(&state, depth)).collect();
+        assert_eq!(counts, vec![7, 49, 343, 2401, 16807, 117649]);
+
+        // Seven move orders fill a column with the first six moves
+        assert_eq!(perft(&state, 7), 823536);
+    }
+
+    #[test]
+    fn test_perft_connect_n() {
+        let connect_four = ConnectNGameState::new_game(6, 7, 4, true).unwrap();
+        assert_eq!(perft(&connect_four, 5), 16807);
+
+        let connect_three = ConnectNGameState::new_game(3, 3, 3, false).unwrap();
+        assert_eq!(perft(&connect_three, 9), 255168);
+    }
+}
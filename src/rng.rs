@@ -0,0 +1,40 @@
+/// Small seedable pseudo-random number generator (SplitMix64).
+///
+/// We do not need cryptographic quality, but the same sequence for the same
+/// seed on every platform so that tests are deterministic. This also avoids
+/// depending on an entropy source which is not available in every Wasm
+/// environment.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniformly distributed value in [0, 1).
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniformly distributed index in [0, `upper`).
+    pub fn gen_index(&mut self, upper: usize) -> usize {
+        (self.next_f64() * upper as f64) as usize
+    }
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self::new(0x5eed)
+    }
+}
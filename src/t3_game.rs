@@ -1,8 +1,12 @@
-use crate::{Board, BoardMove, Cell, Coords, GameState, X_WIN_VALUE};
+use crate::transposition::SIDE_KEY;
+use crate::{
+    Board, BoardMove, Cell, Coords, Error, GameKind, GameOutcome, GameRecord, GameState, Heuristic,
+    IllegalMoveReason, Symmetry, WinningLines, X_WIN_VALUE,
+};
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct T3GameState {
     board: Board,
     last_move: BoardMove,
@@ -10,10 +14,23 @@ pub struct T3GameState {
 
 #[wasm_bindgen]
 impl T3GameState {
+    /// State after `last_move` on `board`, which is not validated, see
+    /// `try_new`.
     pub fn new(board: Board, last_move: BoardMove) -> Self {
         Self { board, last_move }
     }
 
+    /// State after `last_move` on an imported `board`, e.g. filled with
+    /// `Board::set_state`.
+    ///
+    /// Fails if the board is not 3x3 or a win on the board was not
+    /// completed by `last_move`.
+    pub fn try_new(board: Board, last_move: BoardMove) -> Result<T3GameState, Error> {
+        board.validate_size(3, 3, "Tic-tac-toe")?;
+        board.validate_winner(&last_move, 3)?;
+        Ok(Self::new(board, last_move))
+    }
+
     pub fn side(&self) -> Cell {
         self.last_move.side
     }
@@ -21,6 +38,68 @@ impl T3GameState {
     pub fn last_move(&self) -> BoardMove {
         self.last_move
     }
+
+    /// Outcome of the game as `{ kind, winner }` object.
+    #[wasm_bindgen(js_name = outcome)]
+    pub fn outcome_js(&self) -> Result<JsValue, JsValue> {
+        Ok(GameState::outcome(self).to_js_value()?)
+    }
+
+    /// Lines won by the last move as `{ winner, lines }` object, or `null`.
+    #[wasm_bindgen(js_name = winning_lines)]
+    pub fn winning_lines_js(&self) -> Result<JsValue, JsValue> {
+        Ok(WinningLines::option_to_js_value(&self.winning_lines())?)
+    }
+
+    /// Load a position like `3/1X1/3 O`, see `Board::from_notation`.
+    #[wasm_bindgen(js_name = from_notation)]
+    pub fn from_notation_js(notation: &str) -> Result<T3GameState, JsValue> {
+        Self::from_notation(notation).map_err(JsValue::from)
+    }
+
+    /// Board and side to move in the notation of `Board::from_notation`.
+    pub fn to_notation(&self) -> String {
+        let side_to_move = match self.last_move.side {
+            Cell::X => Cell::O,
+            _ => Cell::X,
+        };
+        self.board.to_notation(side_to_move)
+    }
+}
+
+impl T3GameState {
+    /// Winner and every line completed by the last move.
+    pub fn winning_lines(&self) -> Option<WinningLines> {
+        self.board.winning_lines(&self.last_move.coords, 3)
+    }
+
+    pub fn from_notation(notation: &str) -> Result<Self, Error> {
+        let (board, side_to_move) = Board::from_notation(notation)?;
+        board.validate_size(3, 3, "Tic-tac-toe")?;
+
+        let last_move = board.infer_last_move(side_to_move, 3, false)?;
+        Self::try_new(board, last_move)
+    }
+
+    /// Empty record of a game which starts at this state.
+    pub fn new_record(&self) -> GameRecord {
+        GameRecord::new(GameKind::TicTacToe, 3, 3, 3, false, self.to_notation())
+    }
+
+    /// Start state of `record`, which has to be a game of tic-tac-toe.
+    pub fn from_record_start(record: &GameRecord) -> Result<Self, Error> {
+        if record.kind != GameKind::TicTacToe {
+            return Err(Error::InvalidRecord(format!(
+                "Record of {:?} is not tic-tac-toe",
+                record.kind
+            )));
+        }
+
+        match &record.start {
+            Some(start) => Self::from_notation(start),
+            None => Ok(Self::default()),
+        }
+    }
 }
 
 impl Default for T3GameState {
@@ -78,9 +157,58 @@ impl GameState for T3GameState {
         }
     }
 
+    fn heuristic_value(&self, heuristic: Heuristic) -> i32 {
+        match self.outcome() {
+            GameOutcome::Ongoing => heuristic.evaluate(&self.board, 3),
+            _ => self.position_value(),
+        }
+    }
+
     fn side(&self) -> Cell {
         self.last_move.side
     }
+
+    fn validate_coords(&self, coords: &Coords) -> Result<(), IllegalMoveReason> {
+        self.board.validate_placement(coords, false)
+    }
+
+    fn board_size(&self) -> (u32, u32) {
+        (self.board.height(), self.board.width())
+    }
+
+    fn last_move(&self) -> BoardMove {
+        self.last_move
+    }
+
+    fn outcome(&self) -> GameOutcome {
+        self.board.outcome(&self.last_move.coords, 3)
+    }
+
+    fn zobrist_hash(&self) -> u64 {
+        match self.last_move.side {
+            Cell::X => self.board.zobrist_hash() ^ SIDE_KEY,
+            _ => self.board.zobrist_hash(),
+        }
+    }
+
+    fn symmetries(&self) -> Vec<Symmetry> {
+        Symmetry::ALL.to_vec()
+    }
+
+    fn transformed(&self, symmetry: Symmetry) -> Self {
+        Self {
+            board: self.board.transformed(symmetry),
+            last_move: symmetry.map_move(&self.last_move, 3, 3),
+        }
+    }
+
+    fn canonical_hash(&self) -> u64 {
+        let (board, _) = self.board.canonical(&self.symmetries());
+        match self.last_move.side {
+            Cell::X => board.zobrist_hash() ^ SIDE_KEY,
+            _ => board.zobrist_hash(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -88,7 +216,7 @@ mod test {
 
     use super::BoardMove;
     use super::Cell;
-    use super::{Board, Coords, GameState, T3GameState};
+    use super::{Board, Coords, Error, GameOutcome, GameState, T3GameState};
 
     #[test]
     fn test_t3gamestate_expand() {
@@ -119,4 +247,48 @@ mod test {
         let expanded_states = game_state.expand();
         assert_eq!(expanded_states.len(), 3);
     }
+
+    #[test]
+    fn test_t3_notation() {
+        let state = T3GameState::from_notation("XO1/XO1/X2 O").unwrap();
+        assert_eq!(state.last_move(), BoardMove::new(0, 0, Cell::X));
+        assert_eq!(state.outcome(), GameOutcome::Win(Cell::X));
+        assert_eq!(state.to_notation(), "XO1/XO1/X2 O");
+        assert_eq!(T3GameState::default().to_notation(), "3/3/3 X");
+
+        assert!(T3GameState::from_notation("7/7/7/7/7/7 X").is_err());
+    }
+
+    #[test]
+    fn test_t3_try_new() {
+        // X won in the first column, which a last move of O would hide
+        let mut board = Board::new(3, 3);
+        let _ = board.set_state(vec![
+            Cell::X,
+            Cell::O,
+            Cell::Empty,
+            Cell::X,
+            Cell::O,
+            Cell::Empty,
+            Cell::X,
+            Cell::Empty,
+            Cell::Empty,
+        ]);
+        assert!(matches!(
+            T3GameState::try_new(board.clone(), BoardMove::new(1, 1, Cell::O)),
+            Err(Error::InvalidPosition(_))
+        ));
+
+        let state = T3GameState::try_new(board, BoardMove::new(2, 0, Cell::X)).unwrap();
+        assert_eq!(state.outcome(), GameOutcome::Win(Cell::X));
+
+        let default = T3GameState::default();
+        assert!(T3GameState::try_new(default.board, default.last_move).is_ok());
+
+        // Other board sizes cannot be mapped onto the tic-tac-toe board
+        assert_eq!(
+            T3GameState::try_new(Board::new(5, 5), default.last_move).unwrap_err(),
+            Error::InvalidPosition("Tic-tac-toe needs a 3x3 board instead of 5x5".to_owned())
+        );
+    }
 }
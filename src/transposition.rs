@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+
+/// Key which is mixed into the hash of a game state if X did the last move.
+pub const SIDE_KEY: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// Pseudo-random but fixed key for the Zobrist hash of a `Cell` at `idx`.
+///
+/// Instead of storing a table of random numbers per board size, the keys are
+/// derived from the index with the SplitMix64 finalizer. This gives
+/// well-distributed keys for boards of any size.
+pub const fn zobrist_key(idx: usize, side: u8) -> u64 {
+    let mut z = (idx as u64)
+        .wrapping_mul(2)
+        .wrapping_add(side as u64)
+        .wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Cache for values of positions which can be reached by several move orders.
+///
+/// The table is keyed by the Zobrist hash of a game state and counts hits and
+/// misses of lookups so that the benefit can be checked.
+#[derive(Debug)]
+pub struct TranspositionTable<V> {
+    entries: HashMap<u64, V>,
+    hits: usize,
+    misses: usize,
+}
+
+impl<V> Default for TranspositionTable<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> TranspositionTable<V> {
+    pub fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Look up the entry for `hash` and count the lookup as hit or miss.
+    pub fn get(&mut self, hash: u64) -> Option<&V> {
+        match self.entries.get(&hash) {
+            Some(entry) => {
+                self.hits += 1;
+                Some(entry)
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Check for an entry without counting the lookup.
+    pub fn contains(&self, hash: u64) -> bool {
+        self.entries.contains_key(&hash)
+    }
+
+    pub fn insert(&mut self, hash: u64, entry: V) {
+        self.entries.insert(hash, entry);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+}
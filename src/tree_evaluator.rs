@@ -1,12 +1,21 @@
-use super::{Cell, GameState, X_WIN_VALUE};
+use super::{
+    AlphaBetaSearch, Analysis, BoardMove, Cell, DeepeningResult, GameState, Heuristic, MoveScore,
+    SearchBudget, Symmetry, TranspositionTable, X_WIN_VALUE,
+};
+use crate::logging::{error, info, LogEvent};
 use std::collections::VecDeque;
-use web_sys::console;
 
 pub struct TreeEvaluator<T> {
     parent: Vec<usize>,
     children: Vec<Vec<usize>>,
     game_states: Vec<T>,
     worst_case_values: Vec<i32>,
+    /// Whether the game is won or drawn in the state, which is checked once
+    /// when the state is added
+    game_over: Vec<bool>,
+    transpositions: TranspositionTable<usize>,
+    heuristic: Heuristic,
+    merge_symmetries: bool,
 }
 
 impl<'a, T> TreeEvaluator<T>
@@ -14,14 +23,40 @@ where
     T: GameState + Default,
 {
     pub fn new(init_state: T) -> TreeEvaluator<T> {
+        let mut transpositions = TranspositionTable::new();
+        transpositions.insert(init_state.zobrist_hash(), 0);
+
         TreeEvaluator {
             parent: vec![0],
             children: vec![vec![]],
+            game_over: vec![init_state.outcome().is_over()],
             game_states: vec![init_state],
             worst_case_values: vec![0],
+            transpositions,
+            heuristic: Heuristic::default(),
+            merge_symmetries: false,
         }
     }
 
+    /// Set the heuristic to estimate the value of states at the depth limit.
+    ///
+    /// This only applies to states which are expanded from now on.
+    pub fn set_heuristic(&mut self, heuristic: Heuristic) {
+        self.heuristic = heuristic;
+    }
+
+    /// Only keep one of the children which are symmetric to each other.
+    ///
+    /// Symmetric positions have the same value, so searching one of them is
+    /// enough. Only siblings are merged, positions under different parents
+    /// are still shared by their exact hash, so every stored state keeps the
+    /// orientation of the moves leading to it. This applies to states which
+    /// are expanded from now on. Moves to the dropped children are found with
+    /// `symmetric_child`.
+    pub fn set_merge_symmetries(&mut self, merge_symmetries: bool) {
+        self.merge_symmetries = merge_symmetries;
+    }
+
     pub fn new_with_default() -> TreeEvaluator<T> {
         let init_state = T::default();
         Self::new(init_state)
@@ -36,51 +71,118 @@ where
             }
         }
 
-        console::log_1(&format!("Generated {} new nodes", expanded_children.len()).into());
+        info(LogEvent::NodesGenerated {
+            nodes: expanded_children.len(),
+        });
 
         expanded_children
     }
 
+    /// Expand the state at `idx` and return the indexes of newly added
+    /// children.
+    ///
+    /// Children which are already stored in the tree because they were
+    /// reached by a different move order are only linked to `idx` through the
+    /// transposition table. Since they will be expanded through their first
+    /// parent, they are not part of the returned indexes. This turns the tree
+    /// into a directed acyclic graph. In our games, all paths to a position
+    /// have the same length, so every position keeps a well-defined depth.
     fn expand_state(&mut self, idx: usize) -> Option<Vec<usize>> {
-        let g_state = self.game_states.get(idx).expect("Game state");
-        let pos_value = *self.worst_case_values.get(idx).expect("Position value");
-        if pos_value == X_WIN_VALUE || pos_value == -X_WIN_VALUE {
+        if self.is_final(idx) {
             // State is final - skip expansion
             return None;
         }
+        let g_state = self.game_states.get(idx).expect("Game state");
 
         // Expand game state to possible child states
         let mut child_states = g_state.expand();
+        if self.merge_symmetries {
+            child_states = self.merge_symmetric(child_states);
+        }
 
-        // Indexes of child states in the tree data structure
-        let child_idx: Vec<usize> =
-            (self.parent.len()..self.parent.len() + child_states.len()).collect();
+        // Indexes of all child states in the tree data structure and of those
+        // which are added now
+        let mut child_idx: Vec<usize> = Vec::with_capacity(child_states.len());
+        let mut new_child_idx: Vec<usize> = Vec::with_capacity(child_states.len());
+        let mut new_child_states: Vec<T> = Vec::with_capacity(child_states.len());
 
-        // Initialize worst case values to positional values to catch final states
-        let mut worst_case_values: Vec<i32> = child_states
+        for state in child_states {
+            let hash = state.zobrist_hash();
+            match self.transpositions.get(hash) {
+                Some(&existing_idx) => child_idx.push(existing_idx),
+                None => {
+                    let new_idx = self.parent.len() + new_child_states.len();
+                    self.transpositions.insert(hash, new_idx);
+                    child_idx.push(new_idx);
+                    new_child_idx.push(new_idx);
+                    new_child_states.push(state);
+                }
+            }
+        }
+
+        // Initialize worst case values to heuristic values to catch final
+        // states and estimate the value at the depth limit
+        let mut worst_case_values: Vec<i32> = new_child_states
             .iter()
-            .map(|state| state.position_value())
+            .map(|state| state.heuristic_value(self.heuristic))
+            .collect();
+        let mut game_over: Vec<bool> = new_child_states
+            .iter()
+            .map(|state| state.outcome().is_over())
             .collect();
 
         // Set children of parent
         self.children
             .get_mut(idx)
             .expect("Parent")
-            .append(&mut child_idx.clone());
+            .append(&mut child_idx);
 
-        // Add parent for children
-        self.parent.append(&mut vec![idx; child_states.len()]);
-        for _ in 0..child_states.len() {
+        // Add parent for new children
+        self.parent.append(&mut vec![idx; new_child_states.len()]);
+        for _ in 0..new_child_states.len() {
             // Add empty children vectors for children
             self.children.push(vec![]);
         }
 
         // Add child states and positional values
-        self.game_states.append(&mut child_states);
+        self.game_states.append(&mut new_child_states);
         self.worst_case_values.append(&mut worst_case_values);
+        self.game_over.append(&mut game_over);
+
+        // Return new child indexes for expansion
+        Some(new_child_idx)
+    }
+
+    /// Keep one state of every group of symmetric `states`.
+    ///
+    /// States which are already stored in the tree are preferred, e.g. the
+    /// move which was taken back when expanding a state again.
+    fn merge_symmetric(&self, states: Vec<T>) -> Vec<T> {
+        let canonical_hashes: Vec<u64> =
+            states.iter().map(|state| state.canonical_hash()).collect();
+        let mut kept_pos: Vec<usize> = Vec::new();
+        for (pos, state) in states.iter().enumerate() {
+            let stored = self.transpositions.contains(state.zobrist_hash());
+            match kept_pos
+                .iter_mut()
+                .find(|kept| canonical_hashes[**kept] == canonical_hashes[pos])
+            {
+                Some(kept) => {
+                    if stored && !self.transpositions.contains(states[*kept].zobrist_hash()) {
+                        *kept = pos;
+                    }
+                }
+                None => kept_pos.push(pos),
+            }
+        }
+        kept_pos.sort_unstable();
 
-        // Return child indexes for expansion
-        Some(child_idx)
+        states
+            .into_iter()
+            .enumerate()
+            .filter(|(pos, _)| kept_pos.binary_search(pos).is_ok())
+            .map(|(_, state)| state)
+            .collect()
     }
 
     pub fn evaluate_states(&mut self, stop_idx: usize) {
@@ -92,11 +194,12 @@ where
             .collect();
 
         // By traversing the graph in reverse BFS-order, we can be sure that
-        // children are evaluated before their parents.
+        // children are evaluated before their parents. This also holds for
+        // shared children since all paths to them have the same length.
         for idx in reverse_bfs_order {
             let side = self.game_states().get(idx).expect("Game state").side();
             let init_value = *self.worst_case_values.get(idx).expect("Avg value");
-            if init_value == X_WIN_VALUE || init_value == -X_WIN_VALUE {
+            if self.is_final(idx) {
                 // Skip evaluating the worst case of children for final states
                 continue;
             }
@@ -122,7 +225,9 @@ where
                     .max()
                     .expect("save due to .is_empty() check"),
                 (false, Cell::Empty) => {
-                    console::log_1(&"Unexpected empty state in previous move".to_owned().into());
+                    error(LogEvent::Message(
+                        "Unexpected empty state in previous move".to_owned(),
+                    ));
                     init_value
                 }
             };
@@ -131,6 +236,413 @@ where
         }
     }
 
+    /// Check if the state at `idx` needs no expansion.
+    ///
+    /// This is the case if the game is won or drawn, or if the evaluation
+    /// already found that one side can force a win.
+    pub fn is_final(&self, idx: usize) -> bool {
+        let value = *self.worst_case_values.get(idx).expect("Position value");
+        value == X_WIN_VALUE || value == -X_WIN_VALUE || self.game_over[idx]
+    }
+
+    /// Identify the best child of `idx` with an alpha-beta search of `depth`
+    /// plies.
+    ///
+    /// Only the direct children of `idx` are stored in the tree (they are
+    /// expanded on demand). Everything below is searched without storing it.
+    /// Returns the index of the best child, its value and the number of
+    /// searched nodes.
+    pub fn best_child_alpha_beta(
+        &mut self,
+        idx: usize,
+        depth: usize,
+    ) -> Option<(usize, i32, usize)> {
+        if self.children.get(idx)?.is_empty() {
+            self.expand_state(idx);
+        }
+
+        let children_idx = self.children.get(idx)?;
+        let child_states: Vec<&T> = children_idx
+            .iter()
+            .map(|&child_idx| self.game_states.get(child_idx).expect("Child game state"))
+            .collect();
+
+        let mut search = AlphaBetaSearch::new(self.heuristic);
+        let (best_pos, best_value) =
+            search.best_child(self.game_states.get(idx)?, &child_states, depth)?;
+
+        Some((children_idx[best_pos], best_value, search.nodes()))
+    }
+
+    /// Identify the best child of `idx` with an iterative deepening alpha-beta
+    /// search limited by `budget`.
+    ///
+    /// The `best_child` of the result is the index of the child in the tree.
+    /// Returns the result of the deepest finished iteration and the number of
+    /// searched nodes.
+    pub fn best_child_deepening(
+        &mut self,
+        idx: usize,
+        max_depth: usize,
+        budget: SearchBudget,
+    ) -> Option<(DeepeningResult, usize)> {
+        if self.children.get(idx)?.is_empty() {
+            self.expand_state(idx);
+        }
+
+        let children_idx = self.children.get(idx)?;
+        let child_states: Vec<&T> = children_idx
+            .iter()
+            .map(|&child_idx| self.game_states.get(child_idx).expect("Child game state"))
+            .collect();
+
+        let mut search = AlphaBetaSearch::new(self.heuristic);
+        let result = search.iterative_deepening(
+            self.game_states.get(idx)?,
+            &child_states,
+            max_depth,
+            budget,
+        )?;
+
+        Some((
+            DeepeningResult {
+                best_child: children_idx[result.best_child],
+                ..result
+            },
+            search.nodes(),
+        ))
+    }
+
+    /// Drop all states except for the ones reachable from `idx` and the
+    /// ancestors of `idx`.
+    ///
+    /// The ancestors are the line of moves which was played to reach `idx`.
+    /// They only keep their child on this line, so they have to be expanded
+    /// again with `reexpand` to continue the game from them. Returns the new
+    /// index for every old index of a kept state.
+    pub fn prune(&mut self, idx: usize) -> Vec<Option<usize>> {
+        let mut kept_idx: Vec<usize> = Vec::new();
+        let mut ancestor_idx = idx;
+        while ancestor_idx != self.parent[ancestor_idx] {
+            ancestor_idx = self.parent[ancestor_idx];
+            kept_idx.push(ancestor_idx);
+        }
+        kept_idx.reverse();
+        kept_idx.extend(self.bfs_iter(idx));
+
+        self.compact(&kept_idx)
+    }
+
+    /// Make `parent_idx` the parent of its child state at `idx`.
+    ///
+    /// A position shared through a transposition has the parent it was
+    /// created from. When a game reaches it through another parent, this
+    /// makes the parent vector follow the moves which were actually played.
+    pub fn attach_to_parent(&mut self, idx: usize, parent_idx: usize) {
+        if self.parent[idx] == parent_idx {
+            return;
+        }
+
+        let hash = self.game_states[idx].zobrist_hash();
+        self.game_states[idx] = self.game_states[parent_idx]
+            .expand()
+            .into_iter()
+            .find(|state| state.zobrist_hash() == hash)
+            .expect("State is a child of its new parent");
+        self.parent[idx] = parent_idx;
+    }
+
+    /// Expand the state at `idx` again after it was pruned.
+    ///
+    /// Children which are still stored are linked like transpositions, so
+    /// their search work is kept. Returns the indexes of the newly added
+    /// children.
+    pub fn reexpand(&mut self, idx: usize) -> Vec<usize> {
+        self.children[idx].clear();
+        self.expand_state(idx).unwrap_or_default()
+    }
+
+    /// Keep only the states at `kept_idx` and store them in this order.
+    ///
+    /// Children which are not kept are removed from their parents. Shared
+    /// children whose parent is dropped are attached to their first parent in
+    /// the new order. Since their last move belongs to the path they were
+    /// created on, they are replaced by the state expanded from the new
+    /// parent.
+    fn compact(&mut self, kept_idx: &[usize]) -> Vec<Option<usize>> {
+        let mut new_idx: Vec<Option<usize>> = vec![None; self.game_states.len()];
+        for (idx, &old_idx) in kept_idx.iter().enumerate() {
+            new_idx[old_idx] = Some(idx);
+        }
+
+        let mut parent = vec![0; kept_idx.len()];
+        let mut orphans: Vec<usize> = Vec::new();
+        for &old_idx in kept_idx.iter().skip(1) {
+            let idx = new_idx[old_idx].expect("Kept index");
+            parent[idx] = match new_idx[self.parent[old_idx]] {
+                Some(parent_idx) => parent_idx,
+                None => {
+                    orphans.push(idx);
+                    usize::MAX
+                }
+            };
+        }
+
+        let children: Vec<Vec<usize>> = kept_idx
+            .iter()
+            .map(|&old_idx| {
+                self.children[old_idx]
+                    .iter()
+                    .filter_map(|&child_idx| new_idx[child_idx])
+                    .collect()
+            })
+            .collect();
+
+        // Attach orphans to the first parent which reaches them
+        for (idx, children_idx) in children.iter().enumerate() {
+            for &child_idx in children_idx.iter() {
+                if parent[child_idx] == usize::MAX {
+                    parent[child_idx] = idx;
+                }
+            }
+        }
+
+        let mut old_states: Vec<Option<T>> = std::mem::take(&mut self.game_states)
+            .into_iter()
+            .map(Some)
+            .collect();
+        let mut game_states: Vec<T> = kept_idx
+            .iter()
+            .map(|&old_idx| old_states[old_idx].take().expect("Kept state"))
+            .collect();
+
+        for idx in orphans {
+            let hash = game_states[idx].zobrist_hash();
+            game_states[idx] = game_states[parent[idx]]
+                .expand()
+                .into_iter()
+                .find(|state| state.zobrist_hash() == hash)
+                .expect("Orphan is a child of its new parent");
+        }
+
+        self.worst_case_values = kept_idx
+            .iter()
+            .map(|&old_idx| self.worst_case_values[old_idx])
+            .collect();
+        self.game_over = kept_idx
+            .iter()
+            .map(|&old_idx| self.game_over[old_idx])
+            .collect();
+
+        self.transpositions.clear();
+        for (idx, state) in game_states.iter().enumerate() {
+            self.transpositions.insert(state.zobrist_hash(), idx);
+        }
+
+        self.parent = parent;
+        self.children = children;
+        self.game_states = game_states;
+
+        new_idx
+    }
+
+    /// Number of states stored in the tree.
+    pub fn num_nodes(&self) -> usize {
+        self.game_states.len()
+    }
+
+    /// Analyze the state at `idx` with an alpha-beta search of `depth` plies.
+    ///
+    /// Every child is scored with an exact value. The principal variation
+    /// continues below the tree with states which are not stored.
+    pub fn analyze_alpha_beta(&mut self, idx: usize, depth: usize) -> Option<Analysis> {
+        if self.children.get(idx)?.is_empty() {
+            self.expand_state(idx);
+        }
+
+        let children_idx = self.children.get(idx)?;
+        let child_states: Vec<&T> = children_idx
+            .iter()
+            .map(|&child_idx| self.game_states.get(child_idx).expect("Child game state"))
+            .collect();
+
+        let mut search = AlphaBetaSearch::new(self.heuristic);
+        let child_values = search.child_values(&child_states, depth);
+        let (best_pos, value) =
+            search.best_child(self.game_states.get(idx)?, &child_states, depth)?;
+
+        let mut principal_variation = vec![self.child_move(idx, children_idx[best_pos])?];
+        principal_variation.extend(
+            search
+                .principal_variation(child_states[best_pos], depth.saturating_sub(1))
+                .iter()
+                .map(|state| state.last_move()),
+        );
+
+        let moves = children_idx
+            .iter()
+            .zip(child_values)
+            .map(|(&child_idx, score)| MoveScore {
+                board_move: self.child_move(idx, child_idx).expect("Child move"),
+                score,
+            })
+            .collect();
+
+        Some(Analysis {
+            moves,
+            principal_variation,
+            value,
+            depth,
+        })
+    }
+
+    /// Values of all children of `idx` with an alpha-beta search of `depth`
+    /// plies, in the order of the children.
+    pub fn child_values_alpha_beta(&mut self, idx: usize, depth: usize) -> Option<Vec<i32>> {
+        if self.children.get(idx)?.is_empty() {
+            self.expand_state(idx);
+        }
+
+        let child_states: Vec<&T> = self.children[idx]
+            .iter()
+            .map(|&child_idx| self.game_states.get(child_idx).expect("Child game state"))
+            .collect();
+
+        let mut search = AlphaBetaSearch::new(self.heuristic);
+        Some(search.child_values(&child_states, depth))
+    }
+
+    /// Analyze the state at `idx` with the values of the stored tree.
+    ///
+    /// The states below `idx` are evaluated first. The principal variation
+    /// follows the first best child on every level until a leaf, so the depth
+    /// is the length of the principal variation.
+    pub fn analyze_minimax(&mut self, idx: usize) -> Option<Analysis> {
+        self.evaluate_states(idx);
+
+        let moves = self
+            .children
+            .get(idx)?
+            .iter()
+            .map(|&child_idx| MoveScore {
+                board_move: self.child_move(idx, child_idx).expect("Child move"),
+                score: self.worst_case_values[child_idx],
+            })
+            .collect();
+
+        let mut principal_variation = Vec::new();
+        let mut cur_idx = idx;
+        while let Some(best_idx) = self.best_evaluated_child(cur_idx) {
+            principal_variation.push(self.child_move(cur_idx, best_idx)?);
+            cur_idx = best_idx;
+        }
+
+        Some(Analysis {
+            moves,
+            depth: principal_variation.len(),
+            principal_variation,
+            value: self.worst_case_values[idx],
+        })
+    }
+
+    /// Move leading from the state at `parent_idx` to its child at `child_idx`.
+    ///
+    /// Children shared through a transposition store the last move of the
+    /// path they were created on. For the other parents, the move is found by
+    /// expanding the parent again.
+    pub fn child_move(&self, parent_idx: usize, child_idx: usize) -> Option<BoardMove> {
+        let child_state = self.game_states.get(child_idx)?;
+        if self.parent.get(child_idx) == Some(&parent_idx) {
+            return Some(child_state.last_move());
+        }
+
+        let hash = child_state.zobrist_hash();
+        self.game_states
+            .get(parent_idx)?
+            .expand()
+            .iter()
+            .find(|state| state.zobrist_hash() == hash)
+            .map(|state| state.last_move())
+    }
+
+    /// Child of `parent_idx` which is symmetric to the state after
+    /// `board_move`, if the state itself was merged into it.
+    ///
+    /// Returns the index of the child and the symmetry which maps it to the
+    /// state after `board_move`.
+    pub fn symmetric_child(
+        &self,
+        parent_idx: usize,
+        board_move: &BoardMove,
+    ) -> Option<(usize, Symmetry)> {
+        let state = self.game_states[parent_idx]
+            .expand()
+            .into_iter()
+            .find(|state| state.last_move() == *board_move)?;
+        let hash = state.zobrist_hash();
+
+        self.children[parent_idx].iter().find_map(|&child_idx| {
+            let child_state = &self.game_states[child_idx];
+            child_state
+                .symmetries()
+                .into_iter()
+                .find(|&symmetry| child_state.transformed(symmetry).zobrist_hash() == hash)
+                .map(|symmetry| (child_idx, symmetry))
+        })
+    }
+
+    /// Apply `symmetry` to all states reachable from `idx`.
+    ///
+    /// After a move to a merged child was played, this turns the stored
+    /// child and its subtree into the orientation of the game. Values stay
+    /// the same since the positions are equivalent. States which are shared
+    /// with other parts of the tree are transformed as well, so this should
+    /// only be called after pruning the tree to `idx`.
+    pub fn transform_subtree(&mut self, idx: usize, symmetry: Symmetry) {
+        let subtree_idx: Vec<usize> = self.bfs_iter(idx).collect();
+        for state_idx in subtree_idx {
+            self.game_states[state_idx] = self.game_states[state_idx].transformed(symmetry);
+        }
+
+        // The symmetry may map the last move to another piece of the same
+        // position, so take the state with the move from the parent
+        let parent_idx = self.parent[idx];
+        if parent_idx != idx {
+            let hash = self.game_states[idx].zobrist_hash();
+            self.game_states[idx] = self.game_states[parent_idx]
+                .expand()
+                .into_iter()
+                .find(|state| state.zobrist_hash() == hash)
+                .expect("State is a child of its parent");
+        }
+
+        self.transpositions.clear();
+        for (idx, state) in self.game_states.iter().enumerate() {
+            self.transpositions.insert(state.zobrist_hash(), idx);
+        }
+    }
+
+    /// First child of `idx` with the best worst case value for the side to
+    /// move.
+    fn best_evaluated_child(&self, idx: usize) -> Option<usize> {
+        let maximizing = self.game_states[idx].side() == Cell::O;
+
+        self.children[idx]
+            .iter()
+            .fold(None, |best: Option<usize>, &child_idx| {
+                let value = self.worst_case_values[child_idx];
+                match best {
+                    Some(best_idx) if maximizing && value <= self.worst_case_values[best_idx] => {
+                        best
+                    }
+                    Some(best_idx) if !maximizing && value >= self.worst_case_values[best_idx] => {
+                        best
+                    }
+                    _ => Some(child_idx),
+                }
+            })
+    }
+
     pub fn game_states(&self) -> &Vec<T> {
         &self.game_states
     }
@@ -139,10 +651,24 @@ where
         &self.worst_case_values
     }
 
+    pub fn parent(&self) -> &Vec<usize> {
+        &self.parent
+    }
+
     pub fn children(&self) -> &Vec<Vec<usize>> {
         &self.children
     }
 
+    /// Number of expanded children which were already stored in the tree.
+    pub fn transposition_hits(&self) -> usize {
+        self.transpositions.hits()
+    }
+
+    /// Number of expanded children which were added as new nodes.
+    pub fn transposition_misses(&self) -> usize {
+        self.transpositions.misses()
+    }
+
     pub fn expand_states_by(&mut self, start_idx: usize, num_levels: u32) {
         let mut expand_now: VecDeque<usize> = VecDeque::from([start_idx]);
 
@@ -160,16 +686,22 @@ where
     }
 
     pub fn bfs_iter(&'a self, start_idx: usize) -> BfsIterator<'a, T> {
+        let mut visited = vec![false; self.game_states.len()];
+        visited[start_idx] = true;
+
         BfsIterator {
             tree_eval: self,
             buffer: VecDeque::from([start_idx]),
+            visited,
         }
     }
 }
 
+/// Iterator visiting every node reachable from the start index once.
 pub struct BfsIterator<'a, T> {
     tree_eval: &'a TreeEvaluator<T>,
     buffer: VecDeque<usize>,
+    visited: Vec<bool>,
 }
 
 impl<'a, T> Iterator for BfsIterator<'a, T> {
@@ -179,7 +711,14 @@ impl<'a, T> Iterator for BfsIterator<'a, T> {
         match self.buffer.pop_front() {
             Some(idx) => {
                 let children = self.tree_eval.children.get(idx).expect("Children");
-                self.buffer.append(&mut VecDeque::from(children.clone()));
+                for &child_idx in children.iter() {
+                    // Shared children are only visited through their first
+                    // parent in BFS-order.
+                    if !self.visited[child_idx] {
+                        self.visited[child_idx] = true;
+                        self.buffer.push_back(child_idx);
+                    }
+                }
                 Some(idx)
             }
             None => None,
@@ -189,7 +728,12 @@ impl<'a, T> Iterator for BfsIterator<'a, T> {
 
 #[cfg(test)]
 mod test {
-    use crate::{Board, BoardMove, Cell, Coords, T3GameState, TreeEvaluator, X_WIN_VALUE};
+    use crate::{
+        Analysis, Board, BoardMove, Cell, ConnectNGameInterface, Coords, Error, ExpandResult,
+        FiarGameInterface, FiarGameState, GameOutcome, GameState, Heuristic, IllegalMoveReason,
+        SearchBudget, SearchMode, T3GameInterface, T3GameState, T3MctsInterface, TreeEvaluator,
+        X_WIN_VALUE,
+    };
 
     fn get_ref_state() -> T3GameState {
         let mut b1 = Board::new(3, 3);
@@ -227,7 +771,7 @@ mod test {
         assert_eq!(tree_eval.game_states.len(), 4);
         assert_eq!(tree_eval.worst_case_values.len(), 4);
 
-        assert_eq!(tree_eval.children.get(0).unwrap(), &vec![1, 2, 3]);
+        assert_eq!(tree_eval.children.first().unwrap(), &vec![1, 2, 3]);
     }
 
     #[test]
@@ -247,7 +791,7 @@ mod test {
         tree_eval.expand_states_by(0, 2);
 
         let bfs_order: Vec<usize> = tree_eval.bfs_iter(0).collect();
-        assert_eq!(bfs_order, (0..6).into_iter().collect::<Vec<usize>>());
+        assert_eq!(bfs_order, (0..6).collect::<Vec<usize>>());
     }
 
     #[test]
@@ -261,6 +805,198 @@ mod test {
         assert_eq!(tree_eval.worst_case_values[0], X_WIN_VALUE);
     }
 
+    #[test]
+    fn test_transpositions_shared() {
+        let mut tree_eval = TreeEvaluator::new(T3GameState::default());
+        tree_eval.expand_states_by(0, 9);
+
+        // Without sharing transpositions, the full tree has 549946 nodes. There
+        // are only 5478 distinct legal positions.
+        assert_eq!(tree_eval.game_states.len(), 5478);
+        assert_eq!(tree_eval.transposition_misses(), 5477);
+        assert!(tree_eval.transposition_hits() > tree_eval.transposition_misses());
+
+        // Every node is evaluated once and tic-tac-toe remains a draw.
+        assert_eq!(tree_eval.bfs_iter(0).count(), 5478);
+        tree_eval.evaluate_states(0);
+        assert_eq!(tree_eval.worst_case_values[0], 0);
+    }
+
+    #[test]
+    fn test_prune() {
+        let mut tree_eval = TreeEvaluator::new(T3GameState::default());
+        tree_eval.expand_states_by(0, 4);
+        let num_nodes = tree_eval.num_nodes();
+
+        // Play X in the center and O in a corner
+        let center_idx = tree_eval.children[0][4];
+        let corner_idx = tree_eval.children[center_idx][0];
+        let num_reachable = tree_eval.bfs_iter(corner_idx).count();
+        let corner_moves: Vec<BoardMove> = tree_eval.children[corner_idx]
+            .iter()
+            .map(|&child_idx| tree_eval.child_move(corner_idx, child_idx).unwrap())
+            .collect();
+
+        // Only the subtree of the corner and the line leading to it are kept
+        let new_idx = tree_eval.prune(corner_idx);
+        assert_eq!(new_idx[0], Some(0));
+        assert_eq!(new_idx[center_idx], Some(1));
+        assert_eq!(new_idx[corner_idx], Some(2));
+        assert_eq!(tree_eval.num_nodes(), num_reachable + 2);
+        assert!(tree_eval.num_nodes() < num_nodes);
+        assert_eq!(tree_eval.parent.len(), num_reachable + 2);
+        assert_eq!(tree_eval.children.len(), num_reachable + 2);
+        assert_eq!(tree_eval.worst_case_values.len(), num_reachable + 2);
+        assert_eq!(tree_eval.children[0], vec![1]);
+        assert_eq!(tree_eval.children[1], vec![2]);
+
+        // Children and their moves are kept and the stored last moves match
+        // the parents after the remapping
+        assert_eq!(
+            tree_eval.children[2]
+                .iter()
+                .map(|&child_idx| tree_eval.child_move(2, child_idx).unwrap())
+                .collect::<Vec<BoardMove>>(),
+            corner_moves
+        );
+        for idx in 1..tree_eval.num_nodes() {
+            let parent_idx = tree_eval.parent[idx];
+            assert!(tree_eval.children[parent_idx].contains(&idx));
+            let hash = tree_eval.game_states[idx].zobrist_hash();
+            let expanded_move = tree_eval.game_states[parent_idx]
+                .expand()
+                .into_iter()
+                .find(|state| state.zobrist_hash() == hash)
+                .unwrap()
+                .last_move();
+            assert_eq!(tree_eval.game_states[idx].last_move(), expanded_move);
+        }
+
+        // Expansion continues at the leafs of the corner without duplicates
+        let leafs: Vec<usize> = tree_eval
+            .bfs_iter(2)
+            .filter(|&idx| tree_eval.children[idx].is_empty() && !tree_eval.is_final(idx))
+            .collect();
+        for idx in leafs {
+            tree_eval.expand_states_by(idx, 5);
+        }
+        let mut full_eval = TreeEvaluator::new(tree_eval.game_states[2].clone());
+        full_eval.expand_states_by(0, 7);
+        assert_eq!(tree_eval.num_nodes(), full_eval.num_nodes() + 2);
+        tree_eval.evaluate_states(2);
+        full_eval.evaluate_states(0);
+        assert_eq!(
+            tree_eval.worst_case_values[2],
+            full_eval.worst_case_values[0]
+        );
+    }
+
+    #[test]
+    fn test_prune_and_reexpand() {
+        let mut tree_eval = TreeEvaluator::new(T3GameState::default());
+        tree_eval.expand_states_by(0, 9);
+        let num_nodes = tree_eval.num_nodes();
+
+        // Play X(2, 2), O(1, 1), X(0, 0). The last position was created
+        // through X(0, 0) first, but its parent follows the played line.
+        let mut idx = 0;
+        for board_move in [
+            BoardMove::new(2, 2, Cell::X),
+            BoardMove::new(1, 1, Cell::O),
+            BoardMove::new(0, 0, Cell::X),
+        ] {
+            let child_idx = tree_eval.children[idx]
+                .iter()
+                .copied()
+                .find(|&child_idx| tree_eval.child_move(idx, child_idx) == Some(board_move))
+                .unwrap();
+            tree_eval.attach_to_parent(child_idx, idx);
+            idx = tree_eval.prune(child_idx)[child_idx].unwrap();
+        }
+
+        // The line from the start and the subtree of the last move are kept
+        let num_subtree_nodes = tree_eval.bfs_iter(idx).count();
+        assert_eq!(idx, 3);
+        assert_eq!(tree_eval.num_nodes(), num_subtree_nodes + 3);
+        assert!(tree_eval.num_nodes() < num_nodes);
+        assert_eq!(
+            tree_eval.game_states[idx].last_move(),
+            BoardMove::new(0, 0, Cell::X)
+        );
+        // The outcomes which were checked on expansion move with the states
+        assert!(tree_eval.game_over.contains(&true));
+        for (state, &game_over) in tree_eval.game_states.iter().zip(&tree_eval.game_over) {
+            assert_eq!(state.outcome().is_over(), game_over);
+        }
+
+        // Undo all moves by walking up the parent vector
+        let mut undone_moves = Vec::new();
+        while tree_eval.parent[idx] != idx {
+            let parent_idx = tree_eval.parent[idx];
+            undone_moves.push(tree_eval.child_move(parent_idx, idx).unwrap());
+            assert_eq!(tree_eval.children[parent_idx], vec![idx]);
+
+            let new_children = tree_eval.reexpand(parent_idx);
+            assert_eq!(new_children.len() + 1, tree_eval.children[parent_idx].len());
+            assert!(tree_eval.children[parent_idx].contains(&idx));
+            idx = parent_idx;
+        }
+        assert_eq!(
+            undone_moves,
+            vec![
+                BoardMove::new(0, 0, Cell::X),
+                BoardMove::new(1, 1, Cell::O),
+                BoardMove::new(2, 2, Cell::X)
+            ]
+        );
+
+        // The kept subtree was not expanded again
+        assert_eq!(tree_eval.children[0].len(), 9);
+        assert_eq!(tree_eval.num_nodes(), num_subtree_nodes + 3 + 8 + 7 + 6);
+    }
+
+    #[test]
+    fn test_undo_move_expansion_depth() {
+        // Tic-tac-toe on an interface which expands two moves deep
+        let mut game_if = ConnectNGameInterface::new(3, 3, 3, false).unwrap();
+        game_if.set_search_mode(SearchMode::Minimax);
+        while game_if.expand_one_level() == ExpandResult::NotDone {}
+        assert_eq!(game_if.transposition_misses(), 9 + 9 * 8);
+
+        // The undone move keeps its subtree, which is already two moves deep.
+        // Only the alternatives are expanded, to the same depth.
+        assert!(game_if.track_move(BoardMove::new(0, 0, Cell::X)).is_ok());
+        assert!(game_if.undo_move());
+        while game_if.expand_one_level() == ExpandResult::NotDone {}
+        assert_eq!(game_if.transposition_misses(), 2 * (9 + 9 * 8) - (1 + 8));
+    }
+
+    #[test]
+    fn test_child_move_of_transposition() {
+        let mut tree_eval = TreeEvaluator::new(T3GameState::default());
+        tree_eval.expand_states_by(0, 3);
+
+        let find_child = |tree_eval: &TreeEvaluator<T3GameState>, idx: usize, board_move| {
+            tree_eval.children[idx]
+                .iter()
+                .copied()
+                .find(|&child_idx| tree_eval.child_move(idx, child_idx) == Some(board_move))
+                .unwrap()
+        };
+
+        // X(0, 0), O(1, 1), X(2, 2) creates the position first, so it keeps
+        // X(2, 2) as last move. Reached via X(2, 2), O(1, 1), the move is X(0, 0).
+        let first_idx = find_child(&tree_eval, 0, BoardMove::new(2, 2, Cell::X));
+        let second_idx = find_child(&tree_eval, first_idx, BoardMove::new(1, 1, Cell::O));
+        let shared_idx = find_child(&tree_eval, second_idx, BoardMove::new(0, 0, Cell::X));
+
+        assert_ne!(tree_eval.parent[shared_idx], second_idx);
+        assert_eq!(
+            tree_eval.game_states[shared_idx].last_move(),
+            BoardMove::new(2, 2, Cell::X)
+        );
+    }
+
     // This is currently only used for debugging purposes, no real test
     #[test]
     fn test_t3_corner_state() {
@@ -294,4 +1030,325 @@ mod test {
         tree_eval.evaluate_states(0);
         println!("Expansion done");
     }
+
+    fn get_corner_state_cells() -> Vec<Cell> {
+        // X
+        //  O
+        //   X
+        vec![
+            Cell::X,
+            Cell::Empty,
+            Cell::Empty,
+            Cell::Empty,
+            Cell::O,
+            Cell::Empty,
+            Cell::Empty,
+            Cell::Empty,
+            Cell::X,
+        ]
+    }
+
+    fn get_corner_state() -> T3GameState {
+        let mut b1 = Board::new(3, 3);
+        let _ = b1.set_state(get_corner_state_cells());
+
+        T3GameState::new(
+            b1,
+            BoardMove {
+                coords: Coords { row: 2, col: 2 },
+                side: Cell::X,
+            },
+        )
+    }
+
+    #[test]
+    fn test_alpha_beta_matches_minimax() {
+        for (init_state, depth) in [
+            (get_ref_state(), 9),
+            (get_corner_state(), 9),
+            (T3GameState::default(), 9),
+            (T3GameState::default(), 4),
+        ] {
+            let mut minimax_eval = TreeEvaluator::new(init_state.clone());
+            minimax_eval.expand_states_by(0, depth);
+            minimax_eval.evaluate_states(0);
+            let minimax_idx = minimax_eval.best_evaluated_child(0).unwrap();
+            let minimax_value = minimax_eval.worst_case_values[minimax_idx];
+
+            let mut alpha_beta_eval = TreeEvaluator::new(init_state);
+            let (alpha_beta_idx, alpha_beta_value, nodes) = alpha_beta_eval
+                .best_child_alpha_beta(0, depth as usize)
+                .unwrap();
+
+            assert_eq!(
+                minimax_eval.game_states[minimax_idx].last_move(),
+                alpha_beta_eval.game_states[alpha_beta_idx].last_move()
+            );
+            assert_eq!(minimax_value, alpha_beta_value);
+            assert!(nodes < minimax_eval.game_states.len());
+        }
+    }
+
+    #[test]
+    fn test_heuristic_prefers_center() {
+        // Without a heuristic, all opening moves look the same and the first
+        // column is picked.
+        let mut tree_eval = TreeEvaluator::new(FiarGameState::default());
+        let (best_idx, _, _) = tree_eval.best_child_alpha_beta(0, 4).unwrap();
+        assert_eq!(tree_eval.game_states[best_idx].last_move().coords.col, 0);
+
+        let mut tree_eval = TreeEvaluator::new(FiarGameState::default());
+        tree_eval.set_heuristic(Heuristic::Lines);
+        let (best_idx, _, _) = tree_eval.best_child_alpha_beta(0, 4).unwrap();
+        assert_eq!(tree_eval.game_states[best_idx].last_move().coords.col, 3);
+    }
+
+    #[test]
+    fn test_draws_are_final() {
+        let mut b1 = Board::new(3, 3);
+        // XOX
+        // XOO
+        // OX
+        let _ = b1.set_state(vec![
+            Cell::X,
+            Cell::O,
+            Cell::X,
+            Cell::X,
+            Cell::O,
+            Cell::O,
+            Cell::O,
+            Cell::X,
+            Cell::Empty,
+        ]);
+
+        let mut tree_eval = TreeEvaluator::new(T3GameState::new(
+            b1,
+            BoardMove {
+                coords: Coords { row: 2, col: 1 },
+                side: Cell::X,
+            },
+        ));
+        tree_eval.expand_states_by(0, 2);
+
+        // The only move fills the board without a winner
+        assert_eq!(tree_eval.game_states.len(), 2);
+        assert_eq!(tree_eval.game_states[1].outcome(), GameOutcome::Draw);
+        assert!(tree_eval.is_final(1));
+        assert!(!tree_eval.is_final(0));
+
+        tree_eval.evaluate_states(0);
+        assert_eq!(tree_eval.worst_case_values[0], 0);
+    }
+
+    #[test]
+    fn test_iterative_deepening() {
+        // Without limit, the empty tic-tac-toe board is searched to the end
+        let mut tree_eval = TreeEvaluator::new(T3GameState::default());
+        let (result, _) = tree_eval
+            .best_child_deepening(0, 20, SearchBudget::Nodes(usize::MAX))
+            .unwrap();
+        assert_eq!(result.depth, 9);
+        assert_eq!(result.value, 0);
+
+        // The winning move is found in the first iteration
+        let mut tree_eval = TreeEvaluator::new(get_ref_state());
+        let (result, _) = tree_eval
+            .best_child_deepening(0, 20, SearchBudget::Nodes(0))
+            .unwrap();
+        assert_eq!(result.depth, 1);
+        assert_eq!(result.value, X_WIN_VALUE);
+        assert_eq!(
+            tree_eval.game_states[result.best_child].last_move(),
+            BoardMove::new(0, 1, Cell::X)
+        );
+
+        // A node budget stops the deepening deterministically
+        let mut tree_eval = TreeEvaluator::new(FiarGameState::default());
+        tree_eval.set_heuristic(Heuristic::Lines);
+        let (result, nodes) = tree_eval
+            .best_child_deepening(0, 42, SearchBudget::Nodes(2000))
+            .unwrap();
+        assert!(result.depth > 2 && result.depth < 10);
+        assert!(nodes <= 2001);
+        assert_eq!(
+            tree_eval.best_child_deepening(0, 42, SearchBudget::Nodes(2000)),
+            Some((result, nodes))
+        );
+    }
+
+    #[test]
+    fn test_analysis() {
+        // X
+        //  O
+        //   X
+        // -> O has to play on an edge, the corners lose
+        let mut tree_eval = TreeEvaluator::new(get_corner_state());
+        let analysis = tree_eval.analyze_alpha_beta(0, 9).unwrap();
+
+        assert_eq!(analysis.moves.len(), 6);
+        for move_score in analysis.moves.iter() {
+            let Coords { row, col } = move_score.board_move.coords;
+            match (row + col) % 2 {
+                0 => assert_eq!(move_score.score, X_WIN_VALUE),
+                _ => assert_eq!(move_score.score, 0),
+            }
+        }
+        assert_eq!(analysis.value, 0);
+
+        // The game ends in a draw after all remaining six moves
+        assert_eq!(analysis.principal_variation.len(), 6);
+        assert_eq!(
+            analysis.principal_variation[0],
+            BoardMove::new(0, 1, Cell::O)
+        );
+        let mut board = Board::new(3, 3);
+        let _ = board.set_state(get_corner_state_cells());
+        for board_move in analysis.principal_variation.iter() {
+            assert!(board.set_cell(
+                board_move.coords.row,
+                board_move.coords.col,
+                board_move.side
+            ));
+        }
+        assert!(board.is_full());
+
+        // The stored tree yields the same analysis
+        let mut minimax_eval = TreeEvaluator::new(get_corner_state());
+        minimax_eval.expand_states_by(0, 9);
+        let minimax_analysis = minimax_eval.analyze_minimax(0).unwrap();
+        assert_eq!(minimax_analysis.depth, 6);
+        assert_eq!(
+            minimax_analysis,
+            Analysis {
+                depth: 6,
+                ..analysis
+            }
+        );
+    }
+
+    #[test]
+    fn test_merge_symmetries() {
+        let mut full_eval = TreeEvaluator::new(T3GameState::default());
+        full_eval.expand_states_by(0, 9);
+        full_eval.evaluate_states(0);
+
+        let mut tree_eval = TreeEvaluator::new(T3GameState::default());
+        tree_eval.set_merge_symmetries(true);
+        tree_eval.expand_states_by(0, 9);
+        tree_eval.evaluate_states(0);
+
+        // Corner, edge and center are the only different first moves
+        assert_eq!(tree_eval.children[0].len(), 3);
+        assert_eq!(full_eval.num_nodes(), 5478);
+        assert_eq!(tree_eval.num_nodes(), 3481);
+        assert_eq!(
+            tree_eval.worst_case_values[0],
+            full_eval.worst_case_values[0]
+        );
+
+        // A merged move is found through its symmetric twin
+        let corner = BoardMove::new(2, 2, Cell::X);
+        assert!(tree_eval.children[0]
+            .iter()
+            .all(|&idx| tree_eval.game_states[idx].last_move() != corner));
+        let (idx, symmetry) = tree_eval.symmetric_child(0, &corner).unwrap();
+        assert_eq!(
+            tree_eval.game_states[idx].transformed(symmetry).last_move(),
+            corner
+        );
+
+        tree_eval.prune(idx);
+        tree_eval.transform_subtree(1, symmetry);
+        assert_eq!(tree_eval.game_states[1].last_move(), corner);
+        for child_idx in tree_eval.children[1].clone() {
+            let child_move = tree_eval.child_move(1, child_idx).unwrap();
+            assert_ne!(child_move.coords, corner.coords);
+        }
+
+        // Interfaces map the searched twin onto the board of the game
+        let mut game_if = T3GameInterface::new();
+        game_if.set_merge_symmetries(true);
+        while game_if.expand_one_level() == ExpandResult::NotDone {}
+        assert!(game_if.track_move(corner).is_ok());
+        assert_eq!(game_if.get_best_move(), Ok(BoardMove::new(1, 1, Cell::O)));
+        // Both twins of the second corner are blocked on their own side
+        for (board_move, block) in [
+            (BoardMove::new(0, 2, Cell::X), BoardMove::new(1, 2, Cell::O)),
+            (BoardMove::new(2, 0, Cell::X), BoardMove::new(2, 1, Cell::O)),
+        ] {
+            while game_if.expand_one_level() == ExpandResult::NotDone {}
+            assert!(game_if.track_move(board_move).is_ok());
+            assert_eq!(game_if.get_best_move(), Ok(block));
+            assert!(game_if.undo_move() && game_if.undo_move());
+        }
+    }
+
+    #[test]
+    fn test_track_move_reasons() {
+        // Nothing was expanded yet
+        let mut game_if = T3GameInterface::new();
+        assert_eq!(game_if.track_move(BoardMove::new(1, 1, Cell::X)), Ok(()));
+
+        let illegal =
+            |game_if: &mut T3GameInterface, board_move| match game_if.track_move(board_move) {
+                Err(Error::IllegalMove(_, reason)) => reason,
+                result => panic!("Unexpected {:?}", result),
+            };
+        assert_eq!(
+            illegal(&mut game_if, BoardMove::new(0, 0, Cell::X)),
+            IllegalMoveReason::WrongSide
+        );
+        assert_eq!(
+            illegal(&mut game_if, BoardMove::new(1, 1, Cell::O)),
+            IllegalMoveReason::CellOccupied
+        );
+        assert_eq!(
+            illegal(&mut game_if, BoardMove::new(0, 3, Cell::O)),
+            IllegalMoveReason::OutOfBounds
+        );
+
+        let mut game_if = FiarGameInterface::new();
+        for (row, side) in (0..6).rev().zip([Cell::X, Cell::O].into_iter().cycle()) {
+            assert_eq!(game_if.track_move(BoardMove::new(row, 3, side)), Ok(()));
+        }
+        assert_eq!(
+            game_if.validate_move(BoardMove::new(0, 3, Cell::X)),
+            Err(Error::IllegalMove(
+                BoardMove::new(0, 3, Cell::X),
+                IllegalMoveReason::ColumnFull
+            ))
+        );
+        assert!(matches!(
+            game_if.track_move(BoardMove::new(4, 2, Cell::X)),
+            Err(Error::IllegalMove(_, IllegalMoveReason::Floating))
+        ));
+    }
+
+    #[test]
+    fn test_player_move_after_best_move() {
+        // The page only tracks the moves of the player, the worker already
+        // played its own best move
+        let mut game_if = T3GameInterface::new();
+        game_if.expand_one_level();
+        let best_move = game_if.get_best_move_within(50.0).unwrap().best_move;
+        assert!(matches!(
+            game_if.track_move(best_move),
+            Err(Error::IllegalMove(_, IllegalMoveReason::WrongSide))
+        ));
+        let reply = (0..9)
+            .map(|idx| BoardMove::new(idx / 3, idx % 3, Cell::O))
+            .find(|reply| game_if.validate_move(*reply).is_ok())
+            .unwrap();
+        assert_eq!(game_if.track_move(reply), Ok(()));
+        assert!(game_if.get_best_move().is_ok());
+
+        let mut mcts_if = T3MctsInterface::new();
+        mcts_if.get_best_move().unwrap();
+        let reply = (0..9)
+            .map(|idx| BoardMove::new(idx / 3, idx % 3, Cell::O))
+            .find(|reply| mcts_if.validate_move(*reply).is_ok())
+            .unwrap();
+        assert_eq!(mcts_if.track_move(reply), Ok(()));
+        assert!(mcts_if.get_best_move().is_ok());
+    }
 }
@@ -6,6 +6,22 @@ extern "C" {
 }
 
 pub fn set_panic_hook() {
-    #[cfg(feature = "console_error_panic_hook")]
+    #[cfg(all(feature = "console_error_panic_hook", target_arch = "wasm32"))]
     console_error_panic_hook::set_once();
 }
+
+/// Milliseconds since the epoch, usable both in Wasm and natively.
+pub fn now_ms() -> f64 {
+    #[cfg(target_arch = "wasm32")]
+    {
+        js_sys::Date::now()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_secs_f64() * 1000.0)
+            .unwrap_or(0.0)
+    }
+}
@@ -4,6 +4,8 @@ const { Board, Cell, BoardMove, Coords } = wasm_bindgen
 const BOARD_GAP_SIZE = 4
 const BOARD_PADDING = 4
 const CELL_SIZE = 80
+// Time the AI may think about its move
+const AI_TIME_LIMIT_MS = 1000
 
 // There global variables will be set after loading WASM
 var gBoard = null
@@ -12,7 +14,13 @@ var lastMove = null
 var gWorker = null
 var gameActive = true
 var gNumWinner = 3
+var gResetText = ''
 var gWasmMemory = null
+// Played moves as JS objects and moves which were taken back
+var gMoveHistory = []
+var gRedoMoves = []
+// Board indices of the lines which won the game
+var gWinningFields = []
 
 // Setup a board with the given number of rows and columns.
 function setupBoard(rows, cols) {
@@ -44,7 +52,11 @@ function createFields(numFields) {
 }
 
 // Set field with row/column coordinates.
-function setFieldWithCoords(coords) {
+//
+// Moves of the player are tracked in the worker. Moves of the AI and pieces
+// dropped by the worker were already played there, so `trackInWorker` is
+// false for them.
+function setFieldWithCoords(coords, trackInWorker = true) {
   if (gameActive) {
     lastMove.coords = coords
     lastMove.side = lastMove.side == Cell.X ? Cell.O : Cell.X
@@ -54,22 +66,69 @@ function setFieldWithCoords(coords) {
       `Set field row ${lastMove.coords.row} col ${lastMove.coords.col}`,
     )
 
-    gWorker.postMessage({
-      kind: 'track_move',
-      lastMove: lastMove.to_js_value(),
-    })
+    if (trackInWorker) {
+      gWorker.postMessage({
+        kind: 'track_move',
+        lastMove: lastMove.to_js_value(),
+      })
+    }
+    gMoveHistory.push(lastMove.to_js_value())
+    gRedoMoves = []
+
+    const outcome = gBoard.outcome(lastMove.coords, gNumWinner)
+    checkOutcome(outcome)
+  }
+}
+
+// Take back the last move on the board and in the worker.
+function undoMove() {
+  if (gMoveHistory.length == 0) {
+    return
+  }
+
+  const undoneMove = gMoveHistory.pop()
+  gBoard.undo_move(BoardMove.from_js_value(undoneMove))
+  gRedoMoves.push(undoneMove)
+  gWorker.postMessage({ kind: 'undo_move' })
+
+  lastMove =
+    gMoveHistory.length > 0
+      ? BoardMove.from_js_value(gMoveHistory[gMoveHistory.length - 1])
+      : BoardMove.new(0, 0, Cell.O)
+  gameActive = true
+  gWinningFields = []
+  document.getElementById('notification').innerText = gResetText
+  drawBoardFields()
+}
 
-    const winner = gBoard.line_winner(lastMove.coords, gNumWinner)
-    checkWinner(winner)
+// Play the last move which was taken back again.
+function redoMove() {
+  if (gRedoMoves.length == 0) {
+    return
   }
+
+  const redoneMove = gRedoMoves.pop()
+  lastMove = BoardMove.from_js_value(redoneMove)
+  gBoard.redo_move(lastMove)
+  gMoveHistory.push(redoneMove)
+  gWorker.postMessage({ kind: 'redo_move' })
+
+  checkOutcome(gBoard.outcome(lastMove.coords, gNumWinner))
+  drawBoardFields()
 }
 
-// Check if any side won after the last move and update the game if so.
-function checkWinner(winner) {
-  if (winner == Cell.X || winner == Cell.O) {
+// Check if the game is won or drawn after the last move and end it if so.
+function checkOutcome(outcome) {
+  if (outcome.kind == 'Win') {
     gameActive = false
-    const winnerName = winner == Cell.X ? 'X' : 'O'
-    document.getElementById('notification').innerText = `${winnerName} wins!`
+    document.getElementById('notification').innerText = `${outcome.winner} wins!`
+    const winning = gBoard.winning_lines(lastMove.coords, gNumWinner)
+    gWinningFields = winning.lines
+      .flat()
+      .map((coords) => gBoard.get_index(coords.row, coords.col))
+  } else if (outcome.kind == 'Draw') {
+    gameActive = false
+    document.getElementById('notification').innerText = 'Draw!'
   }
 }
 
@@ -82,7 +141,9 @@ function drawBoardFields() {
       boardField.className = 'board-field rounded-corners'
       boardField.innerHTML = ''
     } else {
-      boardField.className = 'board-field-set rounded-corners'
+      boardField.className = gWinningFields.includes(i)
+        ? 'board-field-set board-field-win rounded-corners'
+        : 'board-field-set rounded-corners'
       if (cell == Cell.X) {
         boardField.innerHTML = '<span>X</span>'
       } else {
@@ -94,6 +155,7 @@ function drawBoardFields() {
 
 // Setup the callback functions of the board.
 function setupButtons(resetText) {
+  gResetText = resetText
   const resetButton = document.getElementById('reset-button')
   resetButton.onclick = () => {
     gBoard.reset()
@@ -101,31 +163,55 @@ function setupButtons(resetText) {
     lastMove.coords.row = 0
     lastMove.coords.col = 0
     lastMove.side = Cell.O
+    gMoveHistory = []
+    gRedoMoves = []
+    gWinningFields = []
     drawBoardFields()
     document.getElementById('notification').innerText = resetText
     gameActive = true
   }
 
+  document.getElementById('undo-button').onclick = undoMove
+  document.getElementById('redo-button').onclick = redoMove
+
   const aiMoveButton = document.getElementById('ai-move-button')
   aiMoveButton.onclick = () => {
     if (gameActive) {
-      gWorker.postMessage({ kind: 'get_best_move' })
+      gWorker.postMessage({
+        kind: 'get_best_move',
+        timeLimitMs: AI_TIME_LIMIT_MS,
+      })
     }
   }
 }
 
 // Setup the worker precalculating possible next moves and their avg. values.
 function setupWorker(workerFile) {
-  const worker = new Worker(workerFile)
+  // Forward the query of the page so that the worker can select the engine
+  const worker = new Worker(workerFile + window.location.search)
 
   // Set on-message callback of worker
   worker.onmessage = async (event) => {
     if (event.data.kind == 'best_move') {
+      if (event.data.depth) {
+        console.log(`AI searched to depth ${event.data.depth}`)
+      }
       if (gameActive) {
         const bestMove = BoardMove.from_js_value(event.data.bestMove)
-        setFieldWithCoords(bestMove.coords)
+        setFieldWithCoords(bestMove.coords, false)
+        drawBoardFields()
+      }
+    } else if (event.data.kind == 'dropped') {
+      // The worker already tracked the move of the player
+      if (gameActive) {
+        const landedMove = BoardMove.from_js_value(event.data.landedMove)
+        setFieldWithCoords(landedMove.coords, false)
         drawBoardFields()
       }
+    } else if (event.data.kind == 'error') {
+      const error = event.data.error
+      document.getElementById('notification').innerText =
+        error.message ?? `${error}`
     }
   }
 
@@ -2,7 +2,9 @@ importScripts('./pkg/wasm_board_games.js')
 
 const {
   FiarGameInterface,
+  FiarMctsInterface,
   T3GameInterface,
+  T3MctsInterface,
   BoardMove,
   ExpandResult,
 } = wasm_bindgen
@@ -11,12 +13,15 @@ async function run_worker(gameName) {
   await wasm_bindgen('./pkg/wasm_board_games_bg.wasm')
   console.log('In worker')
 
+  // The engine can be selected with the query of the page, e.g. `?engine=mcts`
+  const engine = new URLSearchParams(self.location.search).get('engine')
+
   var gameIf = null
 
   if (gameName == 't3') {
-    gameIf = T3GameInterface.new()
+    gameIf = engine == 'mcts' ? T3MctsInterface.new() : T3GameInterface.new()
   } else if (gameName == 'fiar') {
-    gameIf = FiarGameInterface.new()
+    gameIf = engine == 'mcts' ? FiarMctsInterface.new() : FiarGameInterface.new()
   } else {
     throw `Unknown gameName ${gameName}`
   }
@@ -41,12 +46,19 @@ async function run_worker(gameName) {
   }
 
   // Interrupt the self-rescheduling expansion calls for `task`
-  // and resume the expansion afterwards
+  // and resume the expansion afterwards. Errors of the engine are thrown as
+  // `{ kind, message }` objects and forwarded to the page.
   function runBetweenExpansion(task) {
     pauseExpansion = true
-    task()
-    pauseExpansion = false
-    setTimeout(expandGraph, 1)
+    try {
+      task()
+    } catch (error) {
+      console.error('Engine error', error)
+      self.postMessage({ kind: 'error', error })
+    } finally {
+      pauseExpansion = false
+      setTimeout(expandGraph, 1)
+    }
   }
 
   // Handle incoming messages
@@ -57,17 +69,50 @@ async function run_worker(gameName) {
         const lastMove = BoardMove.from_js_value(event.data.lastMove)
         gameIf.track_move(lastMove)
       })
+    } else if (kind == 'drop_in_column') {
+      // Only the Four-in-a-row interfaces support dropping by column. A full
+      // column or a finished game is reported as error.
+      runBetweenExpansion(() => {
+        const landedMove = gameIf.drop_in_column(event.data.col)
+        this.postMessage({
+          kind: 'dropped',
+          landedMove: landedMove.to_js_value(),
+        })
+      })
+    } else if (kind == 'undo_move') {
+      runBetweenExpansion(() => {
+        gameIf.undo_move()
+      })
+    } else if (kind == 'redo_move') {
+      runBetweenExpansion(() => {
+        gameIf.redo_move()
+      })
     } else if (kind == 'reset') {
       runBetweenExpansion(() => {
         gameIf.reset()
       })
     } else if (kind == 'get_best_move') {
       runBetweenExpansion(() => {
-        const bestMove = gameIf.get_best_move()
-        this.postMessage({
-          kind: 'best_move',
-          bestMove: bestMove.to_js_value(),
-        })
+        if (event.data.timeLimitMs) {
+          // Search with iterative deepening until the time limit is used up
+          const result = gameIf.get_best_move_within(event.data.timeLimitMs)
+          this.postMessage({
+            kind: 'best_move',
+            bestMove: result.best_move.to_js_value(),
+            depth: result.depth,
+          })
+        } else {
+          const bestMove = gameIf.get_best_move()
+          this.postMessage({
+            kind: 'best_move',
+            bestMove: bestMove.to_js_value(),
+          })
+        }
+      })
+    } else if (kind == 'analyze') {
+      // Only the tree interfaces support the analysis
+      runBetweenExpansion(() => {
+        this.postMessage({ kind: 'analysis', analysis: gameIf.analyze() })
       })
     }
   }